@@ -19,11 +19,12 @@ in
           ./Cargo.toml
           ./Cargo.lock
           ./css-image
+          ./protocols
         ]);
     };
-    nativeBuildInputs = [pkgs.pkg-config pkgs.glib];
+    nativeBuildInputs = [pkgs.pkg-config pkgs.glib pkgs.rustPlatform.bindgenHook];
     buildInputs = [pkgs.pkg-config];
     configurePhase = ''
-      export PKG_CONFIG_PATH=${pkgs.glib.dev}/lib/pkgconfig:${pkgs.cairo.dev}/lib/pkgconfig:${pkgs.libpulseaudio.dev}/lib/pkgconfig
+      export PKG_CONFIG_PATH=${pkgs.glib.dev}/lib/pkgconfig:${pkgs.cairo.dev}/lib/pkgconfig:${pkgs.libpulseaudio.dev}/lib/pkgconfig:${pkgs.pipewire.dev}/lib/pkgconfig
     '';
   }
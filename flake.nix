@@ -27,11 +27,14 @@
           cargo
           cairo
           libpulseaudio
+          pipewire
+          clang
           rustc
           rust-analyzer
           rustfmt
           clippy
         ];
+        LIBCLANG_PATH = "${pkgs.libclang.lib}/lib";
       };
     });
   };
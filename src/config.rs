@@ -1,9 +1,14 @@
 use crate::{
-    util::helpers::{CSS_STRING, TOML_STRING},
+    modules::title::TitleSettings,
+    util::{
+        alerts::Alert,
+        audio::Backend,
+        helpers::{CSS_STRING, TOML_STRING},
+    },
     Cmd,
 };
 use log::{info, warn};
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 use std::{fs, sync::Arc};
 
 pub fn get_css() -> Result<Box<str>, Box<dyn crate::Error>> {
@@ -73,6 +78,8 @@ pub struct Config {
     #[serde(default)]
     pub font: Font,
     #[serde(default)]
+    pub audio_backend: Backend,
+    #[serde(default)]
     pub modules: PositionedModules,
 }
 
@@ -98,17 +105,32 @@ fn height() -> i32 {
 
 #[derive(Deserialize, Serialize)]
 pub struct Module {
+    #[serde(deserialize_with = "command")]
     pub command: Arc<Cmd>,
     #[serde(default = "pos")]
     pub x: f64,
     #[serde(default = "pos")]
     pub y: f64,
+    #[serde(default)]
+    pub alerts: Arc<[Alert]>,
 }
 
 fn pos() -> f64 {
     0.0
 }
 
+// WindowTitle had no settings before, so older configs spell it `command = "WindowTitle"`
+fn command<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Arc<Cmd>, D::Error> {
+    match toml::Value::deserialize(deserializer)? {
+        toml::Value::String(command) if command == "WindowTitle" => {
+            Ok(Arc::new(Cmd::WindowTitle(TitleSettings::default())))
+        }
+        value => Cmd::deserialize(value)
+            .map(Arc::new)
+            .map_err(serde::de::Error::custom),
+    }
+}
+
 #[derive(Clone, Deserialize, Serialize)]
 pub struct Font {
     #[serde(default = "family")]
@@ -147,3 +169,23 @@ impl Default for Font {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn window_title_spellings() {
+        for command in [r#"command = "WindowTitle""#, r#"command.WindowTitle = {}"#] {
+            let module = toml::from_str::<Module>(command).unwrap();
+            assert!(*module.command == Cmd::WindowTitle(TitleSettings::default()));
+        }
+
+        let module =
+            toml::from_str::<Module>(r#"command.WindowTitle = { max_length = 20 }"#).unwrap();
+        let Cmd::WindowTitle(settings) = &*module.command else {
+            panic!("expected WindowTitle");
+        };
+        assert_eq!(settings.max_length, Some(20));
+    }
+}
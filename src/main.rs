@@ -1,5 +1,8 @@
 mod config;
+mod menu;
+mod mixer;
 mod modules;
+mod popup;
 mod surface;
 mod util;
 
@@ -9,7 +12,10 @@ use config::{get_config, get_css, Config};
 use css_image::style::Style;
 use lazy_static::lazy_static;
 use log::{info, warn, LevelFilter};
-use modules::{custom::Cmd, ModuleData};
+use menu::Menu;
+use mixer::Mixer;
+use modules::{custom::Cmd, idle_inhibitor, tray::menu_at, ModuleData, PointerAction};
+use popup::{Content, Popup};
 use rayon::prelude::*;
 use simplelog::{ColorChoice, TermLogger, TerminalMode, ThreadLogMode};
 use smithay_client_toolkit::{
@@ -20,7 +26,10 @@ use smithay_client_toolkit::{
     reexports::{calloop, calloop_wayland_source::WaylandSource},
     registry::{ProvidesRegistryState, RegistryState},
     registry_handlers,
-    seat::{pointer::PointerHandler, SeatHandler, SeatState},
+    seat::{
+        pointer::{PointerEventKind, PointerHandler},
+        Capability, SeatHandler, SeatState,
+    },
     shell::{
         wlr_layer::{Anchor, Layer, LayerShell, LayerShellHandler},
         WaylandSurface,
@@ -34,12 +43,16 @@ use std::{
 };
 use surface::Surface;
 use tokio::sync::broadcast;
-use util::{helpers::TOML_STRING, listeners::Listeners};
+use util::{helpers::TOML_STRING, listeners::Listeners, toplevel};
 use wayland_client::{
     globals::{registry_queue_init, GlobalList},
-    protocol::{wl_output, wl_shm},
-    Connection, QueueHandle,
+    protocol::{wl_output, wl_pointer, wl_shm},
+    Connection, Dispatch, QueueHandle,
+};
+use wayland_protocols::wp::idle_inhibit::zv1::client::{
+    zwp_idle_inhibit_manager_v1::ZwpIdleInhibitManagerV1, zwp_idle_inhibitor_v1::ZwpIdleInhibitorV1,
 };
+use wayland_protocols_wlr::foreign_toplevel::v1::client::zwlr_foreign_toplevel_manager_v1::ZwlrForeignToplevelManagerV1;
 
 lazy_static! {
     pub static ref CSS: Arc<[Style]> = css_image::parse(CSS_STRING).expect(MESSAGE).into();
@@ -63,7 +76,11 @@ struct StatusBar {
     draw_receiver: mpsc::Receiver<()>,
     config: HotConfig,
     first_run: bool,
+    redraw: bool,
     seat_state: SeatState,
+    pointer: Option<wl_pointer::WlPointer>,
+    popup: Option<Popup>,
+    idle_inhibit_manager: Option<ZwpIdleInhibitManagerV1>,
 }
 
 pub struct HotConfig {
@@ -99,6 +116,8 @@ impl StatusBar {
             TOML.clone()
         });
 
+        util::audio::init(config.audio_backend);
+
         let mut listeners = Listeners::new();
         let positions = [
             (Arc::new(Position::Left), &config.modules.left),
@@ -112,7 +131,28 @@ impl StatusBar {
             .filter_map(|(position, module)| {
                 ModuleData::new(&mut listeners, module, position.clone())
             })
-            .collect();
+            .collect::<Vec<_>>();
+
+        // Optional, only the idle inhibitor module needs it
+        let idle_inhibit_manager = globals.bind(qh, 1..=1, ()).ok();
+        if idle_inhibit_manager.is_none()
+            && module_info
+                .iter()
+                .any(|info| matches!(*info.command, Cmd::IdleInhibitor(_)))
+        {
+            warn!("Compositor doesn't support idle inhibit protocol, idle inhibitor won't work");
+        }
+
+        // Binding the manager makes the compositor send every window, so it's only done for the taskbar
+        if module_info
+            .iter()
+            .any(|info| matches!(*info.command, Cmd::Taskbar(_)))
+            && globals
+                .bind::<ZwlrForeignToplevelManagerV1, _, _>(qh, 1..=3, ())
+                .is_err()
+        {
+            warn!("Compositor doesn't support foreign toplevel management, taskbar won't work");
+        }
 
         let config_dir = dirs::config_dir().expect("Failed to get config directory");
         let css_path = config_dir.join(format!("{}/style.css", env!("CARGO_PKG_NAME")));
@@ -137,10 +177,33 @@ impl StatusBar {
             draw_receiver,
             config,
             first_run: true,
+            redraw: false,
             seat_state,
+            pointer: None,
+            popup: None,
+            idle_inhibit_manager,
         }
     }
 
+    /// Creates or destroys inhibitors on the bar surfaces to match the idle inhibitor module
+    fn update_idle_inhibitors(&mut self, qh: &QueueHandle<Self>) {
+        let active = idle_inhibitor::active();
+        let Some(manager) = &self.idle_inhibit_manager else {
+            return;
+        };
+
+        self.surfaces.iter_mut().for_each(|surface| {
+            match (active, surface.idle_inhibitor.take()) {
+                (true, None) => {
+                    surface.idle_inhibitor =
+                        Some(manager.create_inhibitor(surface.layer_surface.wl_surface(), qh, ()))
+                }
+                (false, Some(inhibitor)) => inhibitor.destroy(),
+                (_, inhibitor) => surface.idle_inhibitor = inhibitor,
+            }
+        });
+    }
+
     fn reload_config(&mut self) {
         let mut config_changed = false;
         if self.config.css_listener.try_recv().is_ok() {
@@ -185,12 +248,26 @@ impl StatusBar {
         };
 
         let config = &self.config;
+        let outputs = self.output_names();
         self.module_info.par_iter_mut().for_each(|info| {
-            if info.receiver.try_recv().is_ok() || info.output.is_empty() || config_changed {
-                info.render(config_changed, config);
+            if info.receiver.try_recv().is_ok()
+                || info.output.is_empty()
+                || info.outputs_changed(&outputs)
+                || config_changed
+            {
+                info.render(config_changed, config, &outputs);
             };
         });
     }
+
+    /// Names of the outputs bars are on
+    fn output_names(&self) -> Vec<Box<str>> {
+        self.surfaces
+            .iter()
+            .filter_map(|surface| surface.output_info.name.as_deref())
+            .map(Into::into)
+            .collect()
+    }
 }
 
 fn get_style(css: &[Style], name: &str, format: &str) -> anyhow::Result<HashMap<String, Vec<u8>>> {
@@ -278,10 +355,13 @@ impl OutputHandler for StatusBar {
                 };
 
                 self.surfaces.push(Surface {
+                    output,
                     output_info: info,
                     layer_surface: layer,
                     width: 0,
                     background,
+                    hit_boxes: Vec::new(),
+                    idle_inhibitor: None,
                 });
             }
         }
@@ -317,10 +397,85 @@ impl PointerHandler for StatusBar {
     fn pointer_frame(
         &mut self,
         _conn: &Connection,
-        _qh: &QueueHandle<Self>,
-        _pointer: &wayland_client::protocol::wl_pointer::WlPointer,
-        _events: &[smithay_client_toolkit::seat::pointer::PointerEvent],
+        qh: &QueueHandle<Self>,
+        _pointer: &wl_pointer::WlPointer,
+        events: &[smithay_client_toolkit::seat::pointer::PointerEvent],
     ) {
+        events.iter().for_each(|event| {
+            let on_popup = self
+                .popup
+                .as_ref()
+                .is_some_and(|popup| popup.layer_surface.wl_surface() == &event.surface);
+
+            let action = match &event.kind {
+                PointerEventKind::Leave { .. } if on_popup => {
+                    self.popup = None;
+                    return;
+                }
+                PointerEventKind::Press { button, .. } => match button {
+                    0x110 => PointerAction::LeftClick, // BTN_LEFT
+                    0x111 => PointerAction::RightClick,
+                    0x112 => PointerAction::MiddleClick,
+                    _ => return,
+                },
+                PointerEventKind::Axis { vertical, .. } if vertical.discrete < 0 => {
+                    PointerAction::ScrollUp
+                }
+                PointerEventKind::Axis { vertical, .. } if vertical.discrete > 0 => {
+                    PointerAction::ScrollDown
+                }
+                _ => return,
+            };
+
+            if let Some(popup) = self.popup.as_mut().filter(|_| on_popup) {
+                match popup.handle_input(action, event.position.0, event.position.1) {
+                    Ok(true) => self.popup = None,
+                    Ok(false) => {}
+                    Err(e) => warn!("Failed to handle input\n {}", e),
+                }
+                self.redraw = true;
+                return;
+            }
+
+            let Some((surface, (i, x))) = self
+                .surfaces
+                .iter()
+                .find(|surface| surface.layer_surface.wl_surface() == &event.surface)
+                .and_then(|surface| Some((surface, surface.module_at(event.position.0)?)))
+            else {
+                return;
+            };
+
+            let outputs = self.output_names();
+            let info = &mut self.module_info[i];
+            let content = match (info.command.as_ref(), action) {
+                (Cmd::Audio(_), PointerAction::LeftClick) => Some(Content::Mixer(Mixer::default())),
+                (Cmd::Tray(settings), PointerAction::RightClick) => menu_at(settings, x)
+                    .map(|(destination, path)| Content::Menu(Menu::new(destination, path))),
+                _ => None,
+            };
+
+            match content {
+                // Clicking again closes the popup
+                Some(_) if self.popup.is_some() => self.popup = None,
+                Some(content) => {
+                    self.popup = Some(Popup::new(
+                        &self.compositor_state,
+                        &self.layer_shell,
+                        qh,
+                        surface,
+                        self.config.config.topbar,
+                        event.position.0 as i32 - x,
+                        content,
+                    ))
+                }
+                None => {
+                    info.handle_input(action, x, surface.output_info.name.as_deref());
+                    info.render(false, &self.config, &outputs);
+                }
+            }
+            self.redraw = true;
+        });
     }
 }
 
@@ -333,6 +488,16 @@ impl LayerShellHandler for StatusBar {
         _configure: smithay_client_toolkit::shell::wlr_layer::LayerSurfaceConfigure,
         _serial: u32,
     ) {
+        if let Some(popup) = self
+            .popup
+            .as_mut()
+            .filter(|popup| &popup.layer_surface == layer)
+        {
+            popup.configure();
+            self.redraw = true;
+            return;
+        }
+
         self.surfaces
             .iter_mut()
             .find(|surface| &surface.layer_surface == layer)
@@ -344,8 +509,15 @@ impl LayerShellHandler for StatusBar {
         &mut self,
         _conn: &Connection,
         _qh: &QueueHandle<Self>,
-        _layer: &smithay_client_toolkit::shell::wlr_layer::LayerSurface,
+        layer: &smithay_client_toolkit::shell::wlr_layer::LayerSurface,
     ) {
+        if self
+            .popup
+            .as_ref()
+            .is_some_and(|popup| &popup.layer_surface == layer)
+        {
+            self.popup = None;
+        }
     }
 }
 
@@ -387,8 +559,9 @@ impl SeatHandler for StatusBar {
         &mut self,
         _conn: &Connection,
         _qh: &QueueHandle<Self>,
-        _seat: wayland_client::protocol::wl_seat::WlSeat,
+        seat: wayland_client::protocol::wl_seat::WlSeat,
     ) {
+        toplevel::set_seat(&seat);
     }
 
     fn remove_seat(
@@ -402,10 +575,18 @@ impl SeatHandler for StatusBar {
     fn new_capability(
         &mut self,
         _conn: &Connection,
-        _qh: &QueueHandle<Self>,
-        _seat: wayland_client::protocol::wl_seat::WlSeat,
-        _capability: smithay_client_toolkit::seat::Capability,
+        qh: &QueueHandle<Self>,
+        seat: wayland_client::protocol::wl_seat::WlSeat,
+        capability: Capability,
     ) {
+        // Seats present at startup are only announced through their capabilities
+        toplevel::set_seat(&seat);
+        if capability == Capability::Pointer && self.pointer.is_none() {
+            match self.seat_state.get_pointer(qh, &seat) {
+                Ok(pointer) => self.pointer = Some(pointer),
+                Err(e) => warn!("Failed to get pointer, mouse input disabled\n {}", e),
+            }
+        }
     }
 
     fn remove_capability(
@@ -413,8 +594,13 @@ impl SeatHandler for StatusBar {
         _conn: &Connection,
         _qh: &QueueHandle<Self>,
         _seat: wayland_client::protocol::wl_seat::WlSeat,
-        _capability: smithay_client_toolkit::seat::Capability,
+        capability: Capability,
     ) {
+        if capability == Capability::Pointer {
+            if let Some(pointer) = self.pointer.take() {
+                pointer.release();
+            }
+        }
     }
 }
 
@@ -492,8 +678,12 @@ async fn main() {
 
     setup_listeners(receivers, tx, ping);
     loop {
-        if status_bar.draw_receiver.try_recv().is_ok() || status_bar.first_run {
+        if status_bar.draw_receiver.try_recv().is_ok()
+            || status_bar.first_run
+            || std::mem::take(&mut status_bar.redraw)
+        {
             status_bar.reload_config();
+            status_bar.update_idle_inhibitors(&qh);
             let drawn = status_bar
                 .surfaces
                 .par_iter_mut()
@@ -526,6 +716,13 @@ async fn main() {
                 .reduce_with(|a, b| a || b)
                 .unwrap_or(false);
 
+            if let Some(popup) = &mut status_bar.popup {
+                popup.render(&status_bar.config);
+                if let Err(e) = popup.draw(&status_bar.shm) {
+                    warn!("Failed to draw popup\n {}", e);
+                }
+            }
+
             if drawn {
                 status_bar.first_run = false;
                 START.call_once(|| {
@@ -548,11 +745,36 @@ delegate_shm!(StatusBar);
 delegate_pointer!(StatusBar);
 delegate_seat!(StatusBar);
 
+// Neither the idle inhibit manager nor inhibitors have any events
+impl Dispatch<ZwpIdleInhibitManagerV1, ()> for StatusBar {
+    fn event(
+        _state: &mut Self,
+        _proxy: &ZwpIdleInhibitManagerV1,
+        _event: <ZwpIdleInhibitManagerV1 as wayland_client::Proxy>::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+impl Dispatch<ZwpIdleInhibitorV1, ()> for StatusBar {
+    fn event(
+        _state: &mut Self,
+        _proxy: &ZwpIdleInhibitorV1,
+        _event: <ZwpIdleInhibitorV1 as wayland_client::Proxy>::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+    }
+}
+
 impl ProvidesRegistryState for StatusBar {
     fn registry(&mut self) -> &mut RegistryState {
         &mut self.registry_state
     }
-    registry_handlers![OutputState];
+    registry_handlers![OutputState, SeatState];
 }
 
 fn logger() {
@@ -0,0 +1,81 @@
+use crate::{
+    modules::{generic_render, state_selector, PointerAction},
+    popup::stack_rows,
+    util::tray::{menu_clicked, menu_layout, MenuItem},
+    HotConfig,
+};
+use image::DynamicImage;
+use log::warn;
+
+/// Popup content showing the dbusmenu of a tray item
+pub struct Menu {
+    destination: Box<str>,
+    path: Box<str>,
+    // Submenu currently shown, 0 is the root
+    parent: i32,
+    rows: Vec<(MenuItem, i32, i32)>,
+}
+
+impl Menu {
+    pub fn new(destination: Box<str>, path: Box<str>) -> Self {
+        Self {
+            destination,
+            path,
+            parent: 0,
+            rows: Vec::new(),
+        }
+    }
+
+    pub fn render(&mut self, config: &HotConfig) -> DynamicImage {
+        let items = menu_layout(&self.destination, &self.path, self.parent).unwrap_or_else(|e| {
+            warn!("Failed to get tray menu\n {}", e);
+            Vec::new()
+        });
+        let css = &config.css;
+
+        let images = items
+            .iter()
+            .map(|item| {
+                let label = match item.submenu {
+                    true => format!("{} ›", item.label),
+                    false => item.label.to_string(),
+                };
+                let selector = state_selector(css, "menu", (!item.enabled).then_some("disabled"));
+                generic_render(css, &selector, &label)
+            })
+            .collect::<Vec<_>>();
+
+        let (image, ranges) = stack_rows(config, &images);
+        self.rows = items
+            .into_iter()
+            .zip(ranges)
+            .map(|(item, (top, bottom))| (item, top, bottom))
+            .collect();
+
+        image
+    }
+
+    /// Returns whether the menu should be closed
+    pub fn handle_input(&mut self, action: PointerAction, y: i32) -> anyhow::Result<bool> {
+        let Some((item, _, _)) = self
+            .rows
+            .iter()
+            .find(|(_, top, bottom)| (*top..*bottom).contains(&y))
+        else {
+            return Ok(false);
+        };
+
+        match action {
+            PointerAction::LeftClick if !item.enabled => Ok(false),
+            PointerAction::LeftClick if item.submenu => {
+                self.parent = item.id;
+                Ok(false)
+            }
+            PointerAction::LeftClick => {
+                menu_clicked(&self.destination, &self.path, item.id)?;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+}
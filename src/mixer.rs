@@ -0,0 +1,127 @@
+use crate::{
+    modules::{generic_render, state_selector, PointerAction},
+    popup::stack_rows,
+    util::{
+        audio::{self, Sink, SinkInput},
+        helpers::combine_images,
+        icons::load_icon,
+    },
+    HotConfig,
+};
+use image::DynamicImage;
+use log::warn;
+
+const VOLUME_STEP: u8 = 5;
+
+struct Row {
+    input: SinkInput,
+    top: i32,
+    bottom: i32,
+    // Clicks right of this position move the stream to the next sink
+    sink_x: i32,
+}
+
+/// Popup content listing the audio streams of applications, opened by clicking the audio module
+#[derive(Default)]
+pub struct Mixer {
+    rows: Vec<Row>,
+    sinks: Vec<Sink>,
+}
+
+impl Mixer {
+    pub fn render(&mut self, config: &HotConfig) -> DynamicImage {
+        let (inputs, sinks) = audio::sink_inputs()
+            .and_then(|inputs| Ok((inputs, audio::sinks()?)))
+            .unwrap_or_else(|e| {
+                warn!("Failed to get audio streams\n {}", e);
+                (Vec::new(), Vec::new())
+            });
+        let css = &config.css;
+
+        let (inputs, images): (Vec<_>, Vec<_>) = inputs
+            .into_iter()
+            .map(|input| {
+                let label = match input.muted {
+                    true => format!("{} muted", input.name),
+                    false => format!("{} {}%", input.name, input.volume),
+                };
+                let label = generic_render(
+                    css,
+                    &state_selector(css, "mixer", input.muted.then_some("muted")),
+                    &label,
+                );
+
+                let sink = sinks
+                    .iter()
+                    .find(|sink| sink.index == input.sink)
+                    .map(|sink| &*sink.description)
+                    .unwrap_or_default();
+                let sink = generic_render(css, &state_selector(css, "mixer", Some("sink")), sink);
+
+                let icon = input
+                    .icon
+                    .as_deref()
+                    .and_then(|icon| load_icon(icon, label.height()));
+
+                let mut parts = icon.iter().collect::<Vec<_>>();
+                parts.push(&label);
+                let sink_x = parts.iter().map(|img| img.width()).sum::<u32>() as i32;
+                parts.push(&sink);
+
+                ((input, sink_x), combine_images(&parts))
+            })
+            .unzip();
+
+        let images = match images.is_empty() {
+            true => vec![generic_render(css, "mixer", "No audio streams")],
+            false => images,
+        };
+
+        let (image, ranges) = stack_rows(config, &images);
+        self.rows = inputs
+            .into_iter()
+            .zip(ranges)
+            .map(|((input, sink_x), (top, bottom))| Row {
+                input,
+                top,
+                bottom,
+                sink_x,
+            })
+            .collect();
+        self.sinks = sinks;
+
+        image
+    }
+
+    pub fn handle_input(&self, action: PointerAction, x: i32, y: i32) -> anyhow::Result<()> {
+        let Some(row) = self
+            .rows
+            .iter()
+            .find(|row| (row.top..row.bottom).contains(&y))
+        else {
+            return Ok(());
+        };
+        let input = row.input.clone();
+        let next_sink = self
+            .sinks
+            .iter()
+            .position(|sink| sink.index == input.sink)
+            .map(|current| self.sinks[(current + 1) % self.sinks.len()].index);
+        let sink_clicked = x >= row.sink_x;
+
+        match action {
+            PointerAction::ScrollUp => {
+                audio::set_sink_input_volume(input.index, input.volume.saturating_add(VOLUME_STEP))
+            }
+            PointerAction::ScrollDown => {
+                audio::set_sink_input_volume(input.index, input.volume.saturating_sub(VOLUME_STEP))
+            }
+            PointerAction::LeftClick if sink_clicked => match next_sink {
+                Some(next_sink) => audio::move_sink_input(input.index, next_sink),
+                None => Ok(()),
+            },
+            PointerAction::LeftClick => audio::set_sink_input_mute(input.index, !input.muted),
+            _ => Ok(()),
+        }
+    }
+}
@@ -1,39 +1,135 @@
+use super::PointerAction;
+use lazy_static::lazy_static;
 use serde::{Deserialize, Serialize};
-use std::sync::Arc;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+use zbus::blocking::Connection;
+
+lazy_static! {
+    static ref SYSTEM: Mutex<Option<Connection>> = Mutex::new(None);
+    // Devices found so far, keyed by the requested device name and whether it's a keyboard
+    static ref PATHS: Mutex<HashMap<(Option<Box<str>>, bool), PathBuf>> = Mutex::new(HashMap::new());
+}
 
 #[derive(Deserialize, Serialize, PartialEq)]
 pub struct BacklightSettings {
     pub formatting: Arc<str>,
     #[serde(default)]
     pub icons: Vec<Box<str>>,
+    #[serde(default)]
+    pub device: Option<Box<str>>,
+    #[serde(default)]
+    pub keyboard: bool,
+    #[serde(default = "step")]
+    pub step: u8,
+}
+
+fn step() -> u8 {
+    5
 }
 
-pub fn get_backlight_path() -> anyhow::Result<std::path::PathBuf> {
-    let mut dirs = std::fs::read_dir("/sys/class/backlight")?;
-    let backlight_path = dirs
+fn find_device(settings: &BacklightSettings) -> anyhow::Result<PathBuf> {
+    let class = match settings.keyboard {
+        true => "/sys/class/leds",
+        false => "/sys/class/backlight",
+    };
+
+    let backlight_path = std::fs::read_dir(class)?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
         .find(|entry| {
-            let entry = entry.as_ref().unwrap().path();
-            if entry.join("brightness").exists() && entry.join("max_brightness").exists() {
-                return true;
-            }
+            let name = entry.file_name().unwrap_or_default().to_string_lossy();
+            let matches_device = match &settings.device {
+                Some(device) => name == **device,
+                None => !settings.keyboard || name.ends_with("::kbd_backlight"),
+            };
 
-            false
+            matches_device
+                && entry.join("brightness").exists()
+                && entry.join("max_brightness").exists()
         })
-        .ok_or_else(|| anyhow::anyhow!("Backlight path not found"))??;
+        .ok_or_else(|| anyhow::anyhow!("Backlight path not found"))?;
 
-    Ok(backlight_path.path())
+    Ok(backlight_path)
 }
 
-pub fn backlight_details() -> anyhow::Result<Box<str>> {
-    let path = get_backlight_path()?;
+pub fn get_backlight_path(settings: &BacklightSettings) -> anyhow::Result<PathBuf> {
+    let key = (settings.device.clone(), settings.keyboard);
+    let mut paths = PATHS.lock().unwrap();
+    if let Some(path) = paths.get(&key) {
+        return Ok(path.clone());
+    }
+
+    let path = find_device(settings)?;
+    paths.insert(key, path.clone());
+    Ok(path)
+}
 
+fn read_brightness(path: &Path) -> anyhow::Result<(u32, u32)> {
     let brightness = std::fs::read_to_string(path.join("brightness"))?
         .trim()
-        .parse::<f32>()?;
+        .parse::<u32>()?;
     let max_brightness = std::fs::read_to_string(path.join("max_brightness"))?
         .trim()
-        .parse::<f32>()?;
+        .parse::<u32>()?;
+
+    Ok((brightness, max_brightness))
+}
 
-    let brightness = ((brightness / max_brightness) * 100.0) as u8;
+pub fn backlight_details(settings: &BacklightSettings) -> anyhow::Result<Box<str>> {
+    let path = get_backlight_path(settings)?;
+    let (brightness, max_brightness) = read_brightness(&path)?;
+
+    let brightness = ((brightness as f32 / max_brightness as f32) * 100.0) as u8;
     Ok((brightness).to_string().into())
 }
+
+/// Sets brightness through logind which is allowed for the active session without root
+fn set_brightness(
+    path: &Path,
+    settings: &BacklightSettings,
+    brightness: u32,
+) -> anyhow::Result<()> {
+    let subsystem = match settings.keyboard {
+        true => "leds",
+        false => "backlight",
+    };
+    let name = path
+        .file_name()
+        .ok_or_else(|| anyhow::anyhow!("Invalid backlight path"))?
+        .to_string_lossy();
+
+    let mut system = SYSTEM.lock().unwrap();
+    if system.is_none() {
+        system.replace(Connection::system()?);
+    }
+
+    system.as_ref().unwrap().call_method(
+        Some("org.freedesktop.login1"),
+        "/org/freedesktop/login1/session/auto",
+        Some("org.freedesktop.login1.Session"),
+        "SetBrightness",
+        &(subsystem, &*name, brightness),
+    )?;
+
+    Ok(())
+}
+
+pub fn handle_input(settings: &BacklightSettings, action: PointerAction) -> anyhow::Result<()> {
+    let path = get_backlight_path(settings)?;
+    let (brightness, max_brightness) = read_brightness(&path)?;
+    let step = (max_brightness * settings.step as u32 / 100).max(1);
+
+    let brightness = match action {
+        PointerAction::ScrollUp => (brightness + step).min(max_brightness),
+        PointerAction::ScrollDown => brightness.saturating_sub(step),
+        // Keyboard backlights usually only have a couple of levels so clicking cycles through them
+        PointerAction::LeftClick if settings.keyboard => (brightness + 1) % (max_brightness + 1),
+        _ => return Ok(()),
+    };
+
+    set_brightness(&path, settings, brightness)
+}
@@ -1,5 +1,5 @@
 use serde::{Deserialize, Serialize};
-use std::sync::Arc;
+use std::{collections::HashMap, path::Path, sync::Arc};
 
 #[derive(Deserialize, Serialize, PartialEq)]
 pub struct BatterySettings {
@@ -7,30 +7,204 @@ pub struct BatterySettings {
     #[serde(default)]
     pub icons: Vec<Box<str>>,
     pub interval: u64,
+    #[serde(default)]
+    pub battery_opts: BatteryOpts,
+    #[serde(default)]
+    pub status_icons: HashMap<Box<str>, Box<str>>,
+    #[serde(default = "critical")]
+    pub critical: u8,
 }
 
-#[derive(Deserialize)]
+fn critical() -> u8 {
+    10
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Default)]
 pub enum BatteryOpts {
+    #[default]
     Capacity,
     Status,
+    Power,
+    TimeRemaining,
+}
+
+#[derive(PartialEq, Clone, Copy)]
+enum Status {
+    Charging,
+    Discharging,
+    Full,
+    NotCharging,
+    Unknown,
 }
 
-pub fn battery_details() -> anyhow::Result<Box<str>> {
-    let mut dirs = std::fs::read_dir("/sys/class/power_supply")?;
-    let path = dirs
-        .find(|entry| {
-            let entry = entry.as_ref().unwrap().path();
-            if entry.join("capacity").exists() {
-                return true;
-            }
+impl Status {
+    fn from_sysfs(status: &str) -> Self {
+        match status {
+            "Charging" => Status::Charging,
+            "Discharging" => Status::Discharging,
+            "Full" => Status::Full,
+            "Not charging" => Status::NotCharging,
+            _ => Status::Unknown,
+        }
+    }
 
-            false
-        })
-        .ok_or_else(|| anyhow::anyhow!("Battery not found"))??;
+    fn as_str(&self) -> &'static str {
+        match self {
+            Status::Charging => "charging",
+            Status::Discharging => "discharging",
+            Status::Full => "full",
+            Status::NotCharging => "not_charging",
+            Status::Unknown => "unknown",
+        }
+    }
+}
+
+struct Battery {
+    capacity: f64,
+    // Energy in µWh and power in µW, batteries reporting charge are converted using voltage
+    energy_now: Option<f64>,
+    energy_full: Option<f64>,
+    power: Option<f64>,
+    status: Status,
+}
 
-    let capacity = std::fs::read_to_string(path.path().join("capacity"))?
+fn read_value(path: &Path, name: &str) -> Option<f64> {
+    std::fs::read_to_string(path.join(name))
+        .ok()?
         .trim()
-        .into();
+        .parse::<f64>()
+        .ok()
+}
+
+impl Battery {
+    fn new(path: &Path) -> Option<Self> {
+        let kind = std::fs::read_to_string(path.join("type")).ok()?;
+        // Skip peripherals like mice and headsets which also expose a battery
+        let scope = std::fs::read_to_string(path.join("scope")).unwrap_or_default();
+        if kind.trim() != "Battery" || scope.trim() == "Device" {
+            return None;
+        }
+
+        let capacity = read_value(path, "capacity")?;
+        let status = std::fs::read_to_string(path.join("status"))
+            .map(|status| Status::from_sysfs(status.trim()))
+            .unwrap_or(Status::Unknown);
+
+        let voltage = read_value(path, "voltage_min_design")
+            .or_else(|| read_value(path, "voltage_now"))
+            .map(|voltage| voltage / 1_000_000.0);
+        let from_charge = |name: &str| Some(read_value(path, name)? * voltage?);
+
+        let energy_now = read_value(path, "energy_now").or_else(|| from_charge("charge_now"));
+        let energy_full = read_value(path, "energy_full").or_else(|| from_charge("charge_full"));
+        let power = read_value(path, "power_now").or_else(|| {
+            let voltage = read_value(path, "voltage_now")? / 1_000_000.0;
+            Some(read_value(path, "current_now")? * voltage)
+        });
+
+        Some(Self {
+            capacity,
+            energy_now,
+            energy_full,
+            power: power.map(f64::abs),
+            status,
+        })
+    }
+}
+
+struct Batteries(Vec<Battery>);
+
+impl Batteries {
+    fn new() -> anyhow::Result<Self> {
+        let batteries = std::fs::read_dir("/sys/class/power_supply")?
+            .filter_map(|entry| Battery::new(&entry.ok()?.path()))
+            .collect::<Vec<_>>();
+
+        if batteries.is_empty() {
+            return Err(anyhow::anyhow!("Battery not found"));
+        }
+
+        Ok(Self(batteries))
+    }
+
+    fn energy(&self) -> Option<(f64, f64)> {
+        self.0.iter().try_fold((0.0, 0.0), |(now, full), battery| {
+            Some((now + battery.energy_now?, full + battery.energy_full?))
+        })
+    }
+
+    fn capacity(&self) -> f64 {
+        match self.energy() {
+            Some((now, full)) if full > 0.0 => (now / full * 100.0).min(100.0),
+            _ => self.0.iter().map(|battery| battery.capacity).sum::<f64>() / self.0.len() as f64,
+        }
+    }
+
+    fn power(&self) -> Option<f64> {
+        self.0.iter().map(|battery| battery.power).sum()
+    }
+
+    fn status(&self) -> Status {
+        let any = |status: Status| self.0.iter().any(|battery| battery.status == status);
+
+        if any(Status::Charging) {
+            Status::Charging
+        } else if any(Status::Discharging) {
+            Status::Discharging
+        } else if self.0.iter().all(|battery| battery.status == Status::Full) {
+            Status::Full
+        } else if any(Status::NotCharging) {
+            Status::NotCharging
+        } else {
+            Status::Unknown
+        }
+    }
+
+    /// Hours until empty when discharging and until full when charging
+    fn time_remaining(&self) -> Option<f64> {
+        let (now, full) = self.energy()?;
+        let power = self.power().filter(|power| *power > 0.0)?;
+
+        match self.status() {
+            Status::Discharging => Some(now / power),
+            Status::Charging => Some((full - now).max(0.0) / power),
+            _ => None,
+        }
+    }
+}
+
+pub fn battery_available() -> bool {
+    Batteries::new().is_ok()
+}
+
+/// Text and state from a single scan, the text is `None` when the batteries don't report it
+pub fn battery_details(
+    settings: &BatterySettings,
+) -> anyhow::Result<(Option<Box<str>>, Option<&'static str>)> {
+    let batteries = Batteries::new()?;
+
+    let output = match settings.battery_opts {
+        BatteryOpts::Capacity => Some((batteries.capacity().round() as u8).to_string()),
+        BatteryOpts::Status => Some(batteries.status().as_str().to_string()),
+        BatteryOpts::Power => batteries
+            .power()
+            .map(|power| format!("{:.1}", power / 1_000_000.0)),
+        // Neither charging nor discharging
+        BatteryOpts::TimeRemaining => batteries.time_remaining().map(|hours| {
+            let minutes = (hours * 60.0).round() as u64;
+            format!("{}:{:02}", minutes / 60, minutes % 60)
+        }),
+    };
+
+    Ok((output.map(Into::into), battery_state(settings, &batteries)))
+}
+
+fn battery_state(settings: &BatterySettings, batteries: &Batteries) -> Option<&'static str> {
+    let status = batteries.status();
+
+    if status != Status::Charging && batteries.capacity() <= settings.critical as f64 {
+        return Some("critical");
+    }
 
-    Ok(capacity)
+    Some(status.as_str())
 }
@@ -1,14 +1,23 @@
 use super::{
-    audio::{audio, AudioSettings},
+    audio::{audio, audio_state, AudioSettings},
     backlight::{backlight_details, BacklightSettings},
     battery::{battery_details, BatterySettings},
     cpu::{usage, CpuSettings},
+    disk::{disk_details, DiskSettings},
+    idle_inhibitor::{idle_inhibitor, idle_inhibitor_state, IdleInhibitorSettings},
+    keyboard_layout::{keyboard_layout, KeyboardLayoutSettings},
+    media::{media, media_state, MediaSettings},
     memory::{memory_usage, MemorySettings},
-    persistant_workspaces::{persistant_workspaces, PersistantWorkspacesIcons},
-    title::get_window_title,
-    workspaces::{workspaces, WorkspacesIcons},
+    mode::{mode, ModeSettings},
+    persistant_workspaces::{persistant_workspaces, slots, PersistantWorkspacesIcons},
+    privacy::{privacy, PrivacySettings},
+    scratchpad::{scratchpad, ScratchpadSettings},
+    taskbar::{taskbar, TaskbarSettings},
+    title::{get_window_title, TitleSettings},
+    tray::{tray_details, TraySettings},
+    workspaces::{workspaces, workspaces_state, WorkspacesIcons},
 };
-use crate::util::listeners::Trigger;
+use crate::util::{audio::Device, compositor, listeners::Trigger};
 use log::warn;
 use serde::{Deserialize, Serialize};
 use std::{process::Command, sync::Arc};
@@ -21,9 +30,19 @@ pub enum Cmd {
     Backlight(BacklightSettings),
     Memory(MemorySettings),
     Audio(AudioSettings),
+    Microphone(AudioSettings),
     Cpu(CpuSettings),
     Battery(BatterySettings),
-    WindowTitle,
+    Disk(DiskSettings),
+    Privacy(PrivacySettings),
+    Media(MediaSettings),
+    Tray(TraySettings),
+    Taskbar(TaskbarSettings),
+    KeyboardLayout(KeyboardLayoutSettings),
+    Mode(ModeSettings),
+    Scratchpad(ScratchpadSettings),
+    IdleInhibitor(IdleInhibitorSettings),
+    WindowTitle(TitleSettings),
 }
 
 #[derive(Deserialize, Serialize, PartialEq)]
@@ -57,13 +76,36 @@ pub fn get_command_output(command: &Cmd) -> anyhow::Result<Box<str>> {
                 Err(e)?
             }
         },
-        Cmd::Workspaces(icons) => workspaces(icons),
-        Cmd::PersistantWorkspaces(icons) => persistant_workspaces(&icons.0),
+        Cmd::Workspaces(icons) => workspaces(icons, None)?,
+        Cmd::PersistantWorkspaces(settings) => persistant_workspaces(&slots(settings, None)?),
         Cmd::Memory(settings) => memory_usage(&settings.memory_opts),
-        Cmd::Backlight(_) => backlight_details()?,
+        Cmd::Backlight(settings) => backlight_details(settings)?,
         Cmd::Cpu(_) => usage(),
-        Cmd::Battery(_) => battery_details()?,
-        Cmd::Audio(_) => audio()?,
-        Cmd::WindowTitle => get_window_title().unwrap_or_default(),
+        Cmd::Battery(settings) => battery_details(settings)?
+            .0
+            .ok_or_else(|| anyhow::anyhow!("Not reported by the battery"))?,
+        Cmd::Audio(settings) => audio(settings, Device::Sink)?,
+        Cmd::Microphone(settings) => audio(settings, Device::Source)?,
+        Cmd::Disk(settings) => disk_details(settings)?.0,
+        Cmd::Privacy(settings) => privacy(settings)?,
+        Cmd::Media(settings) => media(settings),
+        Cmd::Tray(_) => tray_details(),
+        Cmd::Taskbar(_) => taskbar(None),
+        Cmd::KeyboardLayout(settings) => keyboard_layout(settings)?,
+        Cmd::Mode(settings) => mode(settings)?,
+        Cmd::Scratchpad(_) => scratchpad()?,
+        Cmd::IdleInhibitor(_) => idle_inhibitor(),
+        Cmd::WindowTitle(settings) => get_window_title(settings)?,
     })
 }
+
+pub fn get_command_state(command: &Cmd) -> Option<&'static str> {
+    match command {
+        Cmd::Audio(_) => audio_state(Device::Sink),
+        Cmd::Microphone(_) => audio_state(Device::Source),
+        Cmd::Media(_) => media_state(),
+        Cmd::IdleInhibitor(_) => Some(idle_inhibitor_state()),
+        Cmd::Workspaces(_) => workspaces_state(&compositor::workspaces(None).ok()?),
+        _ => None,
+    }
+}
@@ -0,0 +1,207 @@
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    ffi::CString,
+    os::unix::ffi::OsStrExt,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+
+lazy_static! {
+    // Previous /proc/diskstats sample per device, rates are computed from the difference
+    static ref SAMPLES: Mutex<HashMap<Box<str>, (Instant, u64, u64)>> = Mutex::new(HashMap::new());
+}
+
+const SECTOR_SIZE: u64 = 512;
+
+#[derive(Deserialize, Serialize, PartialEq)]
+pub struct DiskSettings {
+    pub path: PathBuf,
+    pub interval: u64,
+    pub formatting: Arc<str>,
+    #[serde(default)]
+    pub disk_opts: DiskOpts,
+    #[serde(default)]
+    pub unit: Unit,
+    #[serde(default)]
+    pub warning: Option<u8>,
+    #[serde(default)]
+    pub critical: Option<u8>,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Default)]
+pub enum DiskOpts {
+    Used,
+    Free,
+    Total,
+    #[default]
+    PercUsed,
+    PercFree,
+    Read,
+    Write,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Default)]
+pub enum Unit {
+    #[default]
+    Auto,
+    B,
+    KiB,
+    MiB,
+    GiB,
+    TiB,
+}
+
+struct Usage {
+    total: u64,
+    free: u64,
+    used: u64,
+}
+
+impl Usage {
+    fn perc_used(&self) -> f64 {
+        // Same as df, reserved blocks count neither as used nor as free
+        match self.used + self.free {
+            0 => 0.0,
+            size => (self.used as f64 / size as f64) * 100.0,
+        }
+    }
+}
+
+fn statvfs(path: &Path) -> anyhow::Result<Usage> {
+    let path = CString::new(path.as_os_str().as_bytes())?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(path.as_ptr(), &mut stat) } != 0 {
+        return Err(std::io::Error::last_os_error().into());
+    }
+
+    let block_size = stat.f_frsize as u64;
+    Ok(Usage {
+        total: stat.f_blocks as u64 * block_size,
+        free: stat.f_bavail as u64 * block_size,
+        used: (stat.f_blocks as u64 - stat.f_bfree as u64) * block_size,
+    })
+}
+
+fn format_bytes(bytes: f64, unit: &Unit) -> String {
+    let units = [
+        (Unit::B, "B"),
+        (Unit::KiB, "K"),
+        (Unit::MiB, "M"),
+        (Unit::GiB, "G"),
+        (Unit::TiB, "T"),
+    ];
+
+    let exponent = match unit {
+        Unit::Auto => units
+            .iter()
+            .enumerate()
+            .rev()
+            .find(|(i, _)| bytes >= 1024_f64.powi(*i as i32))
+            .map(|(i, _)| i)
+            .unwrap_or(0),
+        unit => units.iter().position(|(u, _)| u == unit).unwrap_or(0),
+    };
+
+    let value = bytes / 1024_f64.powi(exponent as i32);
+    match exponent {
+        0 => format!("{}{}", value as u64, units[exponent].1),
+        _ => format!("{:.1}{}", value, units[exponent].1),
+    }
+}
+
+/// Finds the block device backing the mount point closest to `path`
+fn get_device(path: &Path) -> anyhow::Result<Box<str>> {
+    let path = path.canonicalize()?;
+    let mounts = std::fs::read_to_string("/proc/mounts")?;
+
+    let device = mounts
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let device = fields.next()?;
+            let mount_point = fields.next()?.replace("\\040", " ");
+            path.starts_with(&mount_point)
+                .then_some((device, mount_point.len()))
+        })
+        .max_by_key(|(_, len)| *len)
+        .map(|(device, _)| device)
+        .ok_or_else(|| anyhow::anyhow!("Mount point for {} not found", path.display()))?;
+
+    // Resolves /dev/mapper/* and /dev/disk/by-* symlinks to the kernel name
+    let device = Path::new(device).canonicalize()?;
+    Ok(device
+        .file_name()
+        .ok_or_else(|| anyhow::anyhow!("Invalid device path"))?
+        .to_string_lossy()
+        .into())
+}
+
+fn io_rate(settings: &DiskSettings) -> anyhow::Result<Box<str>> {
+    let device = get_device(&settings.path)?;
+    let diskstats = std::fs::read_to_string("/proc/diskstats")?;
+
+    let (read, written) = diskstats
+        .lines()
+        .find_map(|line| {
+            let fields = line.split_whitespace().collect::<Vec<_>>();
+            if fields.get(2) != Some(&&*device) {
+                return None;
+            }
+
+            let read = fields.get(5)?.parse::<u64>().ok()?;
+            let written = fields.get(9)?.parse::<u64>().ok()?;
+            Some((read * SECTOR_SIZE, written * SECTOR_SIZE))
+        })
+        .ok_or_else(|| anyhow::anyhow!("Device {device} not found in /proc/diskstats"))?;
+
+    let now = Instant::now();
+    let previous = SAMPLES.lock().unwrap().insert(device, (now, read, written));
+
+    let rate = match previous {
+        Some((time, prev_read, prev_written)) => {
+            let elapsed = now.duration_since(time).as_secs_f64();
+            let bytes = match settings.disk_opts {
+                DiskOpts::Read => read.saturating_sub(prev_read),
+                _ => written.saturating_sub(prev_written),
+            };
+
+            if elapsed > 0.0 {
+                bytes as f64 / elapsed
+            } else {
+                0.0
+            }
+        }
+        None => 0.0,
+    };
+
+    Ok(format!("{}/s", format_bytes(rate, &settings.unit)).into())
+}
+
+/// Text and state of the module, both are derived from a single statvfs call
+pub fn disk_details(settings: &DiskSettings) -> anyhow::Result<(Box<str>, Option<&'static str>)> {
+    let usage = statvfs(&settings.path)?;
+
+    let output = match settings.disk_opts {
+        DiskOpts::Used => format_bytes(usage.used as f64, &settings.unit).into(),
+        DiskOpts::Free => format_bytes(usage.free as f64, &settings.unit).into(),
+        DiskOpts::Total => format_bytes(usage.total as f64, &settings.unit).into(),
+        DiskOpts::PercUsed => (usage.perc_used() as u8).to_string().into(),
+        DiskOpts::PercFree => (100 - usage.perc_used() as u8).to_string().into(),
+        DiskOpts::Read | DiskOpts::Write => io_rate(settings)?,
+    };
+
+    Ok((output, disk_state(settings, &usage)))
+}
+
+fn disk_state(settings: &DiskSettings, usage: &Usage) -> Option<&'static str> {
+    let perc_used = usage.perc_used() as u8;
+
+    match (settings.warning, settings.critical) {
+        (_, Some(critical)) if perc_used >= critical => Some("critical"),
+        (Some(warning), _) if perc_used >= warning => Some("warning"),
+        _ => None,
+    }
+}
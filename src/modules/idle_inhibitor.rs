@@ -0,0 +1,82 @@
+use super::PointerAction;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fs,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+use tokio::sync::broadcast;
+
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+
+lazy_static! {
+    static ref TX: broadcast::Sender<()> = broadcast::Sender::new(1);
+}
+
+#[derive(Deserialize, Serialize, PartialEq)]
+pub struct IdleInhibitorSettings {
+    pub formatting: Arc<str>,
+    #[serde(default)]
+    pub status_icons: HashMap<Box<str>, Box<str>>,
+    #[serde(default)]
+    pub persist: bool,
+}
+
+fn state_path() -> Option<PathBuf> {
+    dirs::state_dir()
+        .or_else(dirs::cache_dir)
+        .map(|dir| dir.join(format!("{}/idle_inhibitor", env!("CARGO_PKG_NAME"))))
+}
+
+/// Restores the persisted state and returns a receiver notified on every toggle
+pub fn init(settings: &IdleInhibitorSettings) -> broadcast::Receiver<()> {
+    if settings.persist {
+        let active = state_path()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .is_some_and(|state| state.trim() == "1");
+        ACTIVE.store(active, Ordering::Relaxed);
+    }
+
+    TX.subscribe()
+}
+
+/// Whether idle should currently be inhibited, inhibitors on the bar surfaces follow this
+pub fn active() -> bool {
+    ACTIVE.load(Ordering::Relaxed)
+}
+
+pub fn idle_inhibitor() -> Box<str> {
+    idle_inhibitor_state().into()
+}
+
+pub fn idle_inhibitor_state() -> &'static str {
+    match active() {
+        true => "activated",
+        false => "deactivated",
+    }
+}
+
+pub fn handle_input(settings: &IdleInhibitorSettings, action: PointerAction) -> anyhow::Result<()> {
+    if action != PointerAction::LeftClick {
+        return Ok(());
+    }
+
+    let active = !ACTIVE.fetch_xor(true, Ordering::Relaxed);
+    _ = TX.send(());
+
+    if settings.persist {
+        let path = state_path()
+            .ok_or_else(|| anyhow::anyhow!("State directory not found, state won't persist"))?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, if active { "1" } else { "0" })?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,123 @@
+use super::PointerAction;
+use crate::util::compositor;
+use lazy_static::lazy_static;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, path::Path, sync::Arc};
+
+lazy_static! {
+    // Layout descriptions reported by compositors, e.g. "English (US)", to xkb layout names, e.g. "us"
+    static ref SHORT_NAMES: HashMap<Box<str>, Box<str>> = short_names().unwrap_or_else(|e| {
+        warn!("Failed to read xkb layout names\n {}", e);
+        HashMap::new()
+    });
+}
+
+#[derive(Deserialize, Serialize, PartialEq)]
+pub struct KeyboardLayoutSettings {
+    pub formatting: Arc<str>,
+    // Shows the xkb layout name instead of the full one, e.g. "us" instead of "English (US)"
+    #[serde(default)]
+    pub short: bool,
+    // Full layout name to the text displayed for it, e.g. "English (US)" = "us"
+    #[serde(default)]
+    pub layouts: HashMap<Box<str>, Box<str>>,
+}
+
+/// Contents of every `<tag>` in `text`
+fn tags<'a>(text: &'a str, tag: &str) -> Vec<&'a str> {
+    let (open, close) = (format!("<{tag}>"), format!("</{tag}>"));
+    text.split(&open)
+        .skip(1)
+        .filter_map(|rest| Some(rest.split_once(&close)?.0.trim()))
+        .collect()
+}
+
+fn short_names() -> anyhow::Result<HashMap<Box<str>, Box<str>>> {
+    let root = std::env::var("XKB_CONFIG_ROOT").unwrap_or("/usr/share/X11/xkb".into());
+    let rules = std::fs::read_to_string(Path::new(&root).join("rules/evdev.xml"))?;
+    Ok(parse_rules(&rules))
+}
+
+/// Variants are listed inside their layout in the xkb rules and share its name
+fn parse_rules(rules: &str) -> HashMap<Box<str>, Box<str>> {
+    tags(rules, "layout")
+        .into_iter()
+        .filter_map(|layout| {
+            let name = *tags(layout, "name").first()?;
+            Some(
+                tags(layout, "description")
+                    .into_iter()
+                    .map(move |description| (description.into(), name.into())),
+            )
+        })
+        .flatten()
+        .collect()
+}
+
+pub fn keyboard_layout(settings: &KeyboardLayoutSettings) -> anyhow::Result<Box<str>> {
+    let layout = compositor::get()?.keyboard_layout()?;
+
+    if let Some(name) = settings.layouts.get(&layout) {
+        return Ok(name.clone());
+    }
+
+    Ok(match settings.short {
+        true => SHORT_NAMES.get(&layout).cloned().unwrap_or(layout),
+        false => layout,
+    })
+}
+
+pub fn handle_input(action: PointerAction) -> anyhow::Result<()> {
+    match action {
+        PointerAction::LeftClick | PointerAction::ScrollDown => {
+            compositor::get()?.switch_keyboard_layout(true)
+        }
+        PointerAction::RightClick | PointerAction::ScrollUp => {
+            compositor::get()?.switch_keyboard_layout(false)
+        }
+        PointerAction::MiddleClick => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn xkb_names() {
+        let rules = r#"
+            <layoutList>
+              <layout>
+                <configItem>
+                  <name>us</name>
+                  <shortDescription>en</shortDescription>
+                  <description>English (US)</description>
+                </configItem>
+                <variantList>
+                  <variant>
+                    <configItem>
+                      <name>dvorak</name>
+                      <description>English (Dvorak)</description>
+                    </configItem>
+                  </variant>
+                </variantList>
+              </layout>
+              <layout>
+                <configItem>
+                  <name>pl</name>
+                  <description>Polish</description>
+                </configItem>
+              </layout>
+            </layoutList>
+        "#;
+
+        let names = parse_rules(rules);
+        assert_eq!(names.get("English (US)").map(|name| &**name), Some("us"));
+        assert_eq!(
+            names.get("English (Dvorak)").map(|name| &**name),
+            Some("us")
+        );
+        assert_eq!(names.get("Polish").map(|name| &**name), Some("pl"));
+    }
+}
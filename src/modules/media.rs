@@ -0,0 +1,56 @@
+use super::PointerAction;
+use crate::util::{
+    helpers::truncate,
+    mpris::{call, current_player},
+};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, sync::Arc};
+
+#[derive(Deserialize, Serialize, PartialEq)]
+pub struct MediaSettings {
+    pub formatting: Arc<str>,
+    #[serde(default = "track")]
+    pub track: Box<str>,
+    #[serde(default)]
+    pub max_length: Option<usize>,
+    #[serde(default)]
+    pub status_icons: HashMap<Box<str>, Box<str>>,
+}
+
+fn track() -> Box<str> {
+    "%a - %t".into()
+}
+
+/// Track of the most recently active player, empty when there is none
+pub fn media(settings: &MediaSettings) -> Box<str> {
+    let Some(player) = current_player() else {
+        return "".into();
+    };
+    // Players without metadata are hidden rather than showing the bare separators
+    if [&player.artist, &player.title, &player.album]
+        .iter()
+        .all(|field| field.is_empty())
+    {
+        return "".into();
+    }
+
+    let track = settings
+        .track
+        .replace("%a", &player.artist)
+        .replace("%t", &player.title)
+        .replace("%b", &player.album);
+
+    truncate(&track, settings.max_length).into()
+}
+
+pub fn media_state() -> Option<&'static str> {
+    current_player().map(|player| player.status.as_str())
+}
+
+pub fn handle_input(action: PointerAction) -> anyhow::Result<()> {
+    call(match action {
+        PointerAction::LeftClick => "PlayPause",
+        PointerAction::RightClick | PointerAction::ScrollDown => "Next",
+        PointerAction::MiddleClick | PointerAction::ScrollUp => "Previous",
+    })
+}
@@ -3,26 +3,43 @@ pub mod backlight;
 pub mod battery;
 pub mod cpu;
 pub mod custom;
+pub mod disk;
+pub mod idle_inhibitor;
+pub mod keyboard_layout;
+pub mod media;
 pub mod memory;
+pub mod mode;
 pub mod network;
 pub mod persistant_workspaces;
+pub mod privacy;
+pub mod scratchpad;
+pub mod taskbar;
 pub mod title;
+pub mod tray;
 pub mod workspaces;
 
-use std::{ops::Deref, sync::Arc};
+use std::{collections::HashMap, ops::Deref, sync::Arc};
 
 use self::{
     audio::AudioSettings,
     backlight::{get_backlight_path, BacklightSettings},
-    battery::{battery_details, BatterySettings},
+    battery::{battery_available, battery_details, BatterySettings},
     cpu::CpuSettings,
-    custom::{get_command_output, Cmd},
+    custom::{get_command_output, get_command_state, Cmd},
+    disk::disk_details,
+    idle_inhibitor::IdleInhibitorSettings,
+    media::MediaSettings,
     memory::MemorySettings,
 };
 use crate::{
     config::Module,
     get_style,
-    util::listeners::{Listeners, Trigger},
+    util::{
+        alerts::Alerts,
+        compositor,
+        listeners::{Listeners, Trigger},
+        toplevel,
+    },
     HotConfig, Position, CSS, MESSAGE,
 };
 use css_image::style::Style;
@@ -30,13 +47,28 @@ use image::{ColorType, DynamicImage};
 use log::warn;
 use tokio::sync::broadcast;
 
+#[derive(Clone, Copy, PartialEq)]
+pub enum PointerAction {
+    LeftClick,
+    RightClick,
+    MiddleClick,
+    ScrollUp,
+    ScrollDown,
+}
+
 pub struct ModuleData {
     pub output: Box<str>,
+    pub state: Option<&'static str>,
     pub command: Arc<Cmd>,
     pub format: Arc<str>,
     pub receiver: broadcast::Receiver<()>,
     pub cache: DynamicImage,
+    // Output and image for each output name, used by modules showing something different on every bar
+    pub outputs: HashMap<Box<str>, (Box<str>, DynamicImage, Vec<(i32, i32)>)>,
+    // Horizontal range of every icon, only known for persistant workspaces and the taskbar
+    pub glyphs: Vec<(i32, i32)>,
     pub position: Arc<Position>,
+    pub alerts: Alerts,
 }
 
 impl ModuleData {
@@ -46,19 +78,37 @@ impl ModuleData {
         position: Arc<Position>,
     ) -> Option<Self> {
         let (receiver, format) = match &module.command.deref() {
-            Cmd::Workspaces(_) | Cmd::WindowTitle | Cmd::PersistantWorkspaces(_) => {
+            Cmd::KeyboardLayout(settings) => (
+                listeners.new_workspace_listener()?,
+                settings.formatting.clone(),
+            ),
+            Cmd::Scratchpad(settings) => {
+                if let Err(e) = scratchpad::scratchpad_windows() {
+                    warn!("Scratchpad could not be read, deactivating module\n {}", e);
+                    return None;
+                }
+                (
+                    listeners.new_workspace_listener()?,
+                    settings.formatting.clone(),
+                )
+            }
+            Cmd::Mode(settings) => {
+                if let Err(e) = mode::mode_name() {
+                    warn!(
+                        "Binding mode could not be read, deactivating module\n {}",
+                        e
+                    );
+                    return None;
+                }
+                (
+                    listeners.new_workspace_listener()?,
+                    settings.formatting.clone(),
+                )
+            }
+            Cmd::Workspaces(_) | Cmd::WindowTitle(_) | Cmd::PersistantWorkspaces(_) => {
                 let formatting: Arc<str> = Arc::from("%s");
                 (listeners.new_workspace_listener()?, formatting.clone())
             }
-            Cmd::Memory(MemorySettings { .. })
-            | Cmd::Cpu(CpuSettings { .. })
-            | Cmd::Battery(BatterySettings { .. })
-                if matches!(&module.command.deref(), Cmd::Battery(_))
-                    && battery_details().is_err() =>
-            {
-                warn!("Battery not found, deactivating module");
-                return None;
-            }
             Cmd::Memory(MemorySettings {
                 interval,
                 formatting,
@@ -68,14 +118,32 @@ impl ModuleData {
                 interval,
                 formatting,
                 ..
-            })
-            | Cmd::Battery(BatterySettings {
-                interval,
-                formatting,
-                ..
             }) => (listeners.new_time_listener(*interval), formatting.clone()),
+            Cmd::Battery(settings) => {
+                if !battery_available() {
+                    warn!("Battery not found, deactivating module");
+                    return None;
+                }
+                (
+                    listeners.new_uevent_listener("power_supply", settings.interval),
+                    settings.formatting.clone(),
+                )
+            }
+            Cmd::Disk(settings) => {
+                if disk_details(settings).is_err() {
+                    warn!(
+                        "Disk usage for {} could not be read, deactivating module",
+                        settings.path.display()
+                    );
+                    return None;
+                }
+                (
+                    listeners.new_time_listener(settings.interval),
+                    settings.formatting.clone(),
+                )
+            }
             Cmd::Backlight(settings) => {
-                if let Ok(path) = get_backlight_path().map(|path| path.join("brightness")) {
+                if let Ok(path) = get_backlight_path(settings).map(|path| path.join("brightness")) {
                     (
                         listeners.new_file_listener(&path),
                         settings.formatting.clone(),
@@ -85,10 +153,20 @@ impl ModuleData {
                     return None;
                 }
             }
-            Cmd::Audio(settings) => (
+            Cmd::Audio(settings) | Cmd::Microphone(settings) => (
                 listeners.new_volume_change_listener(),
                 settings.formatting.clone(),
             ),
+            Cmd::Privacy(settings) => (
+                listeners.new_volume_change_listener(),
+                settings.formatting.clone(),
+            ),
+            Cmd::Media(settings) => (listeners.new_media_listener(), settings.formatting.clone()),
+            Cmd::Tray(_) => (listeners.new_tray_listener(), Arc::from("%s")),
+            Cmd::Taskbar(_) => (toplevel::subscribe(), Arc::from("%s")),
+            Cmd::IdleInhibitor(settings) => {
+                (idle_inhibitor::init(settings), settings.formatting.clone())
+            }
             Cmd::Custom(settings) => {
                 let trigger = match &settings.event {
                     Trigger::WorkspaceChanged => listeners.new_workspace_listener()?,
@@ -102,23 +180,228 @@ impl ModuleData {
 
         Some(ModuleData {
             output: "".into(),
+            state: None,
             command: module.command.clone(),
             format,
             receiver,
             cache: DynamicImage::new(0, 0, ColorType::L8),
+            outputs: HashMap::new(),
+            glyphs: Vec::new(),
             position,
+            alerts: Alerts::new(module.alerts.clone()),
         })
     }
 
-    pub fn render(&mut self, config_changed: bool, config: &HotConfig) {
-        let output =
-            get_command_output(&self.command).unwrap_or_else(|_| config.config.unkown.clone());
-        if output != self.output || config_changed {
+    /// Handles pointer input, `x` is relative to the left edge of the module
+    pub fn handle_input(&self, action: PointerAction, x: i32, output: Option<&str>) {
+        let result = match &self.command.deref() {
+            Cmd::Workspaces(icons) => workspaces::handle_input(
+                action,
+                x,
+                self.glyphs(output),
+                output.filter(|_| !icons.all_outputs),
+            ),
+            Cmd::PersistantWorkspaces(settings) => persistant_workspaces::handle_input(
+                settings,
+                action,
+                x,
+                self.glyphs(output),
+                output.filter(|_| !settings.all_outputs),
+            ),
+            Cmd::Backlight(settings) => backlight::handle_input(settings, action),
+            Cmd::Media(_) => media::handle_input(action),
+            Cmd::KeyboardLayout(_) => keyboard_layout::handle_input(action),
+            Cmd::Scratchpad(settings) => scratchpad::handle_input(settings, action),
+            Cmd::IdleInhibitor(settings) => idle_inhibitor::handle_input(settings, action),
+            Cmd::Tray(settings) => tray::handle_input(settings, action, x),
+            Cmd::Taskbar(settings) => taskbar::handle_input(
+                action,
+                x,
+                self.glyphs(output),
+                output.filter(|_| !settings.all_outputs),
+            ),
+            _ => Ok(()),
+        };
+
+        if let Err(e) = result {
+            warn!("Failed to handle input\n {}", e);
+        }
+    }
+
+    /// Image drawn on the bar of given output
+    pub fn cache(&self, output: Option<&str>) -> &DynamicImage {
+        output
+            .and_then(|output| self.outputs.get(output))
+            .map_or(&self.cache, |(_, cache, _)| cache)
+    }
+
+    fn glyphs(&self, output: Option<&str>) -> &[(i32, i32)] {
+        output
+            .and_then(|output| self.outputs.get(output))
+            .map_or(&self.glyphs, |(_, _, glyphs)| glyphs)
+    }
+
+    fn per_output(&self) -> bool {
+        match self.command.deref() {
+            Cmd::Workspaces(icons) => !icons.all_outputs,
+            Cmd::PersistantWorkspaces(settings) => !settings.all_outputs,
+            Cmd::Taskbar(settings) => !settings.all_outputs,
+            _ => false,
+        }
+    }
+
+    /// Whether a bar was added since the module was rendered
+    pub fn outputs_changed(&self, outputs: &[Box<str>]) -> bool {
+        self.per_output()
+            && outputs
+                .iter()
+                .any(|output| !self.outputs.contains_key(output))
+    }
+
+    /// Workspace modules only show workspaces of the output their bar is on
+    fn render_outputs(&mut self, config_changed: bool, config: &HotConfig, outputs: &[Box<str>]) {
+        if !self.per_output() {
+            return;
+        }
+
+        self.outputs.retain(|name, _| outputs.contains(name));
+        for name in outputs {
+            if let Some(rendered) = self.render_output(config_changed, config, Some(name)) {
+                self.outputs.insert(name.clone(), rendered);
+            }
+        }
+    }
+
+    /// Renders the module for given output, `None` when nothing changed
+    fn render_output(
+        &self,
+        config_changed: bool,
+        config: &HotConfig,
+        output: Option<&str>,
+    ) -> Option<(Box<str>, DynamicImage, Vec<(i32, i32)>)> {
+        let previous = match output {
+            Some(output) => self.outputs.get(output).map(|(previous, _, _)| previous),
+            None => Some(&self.output),
+        };
+
+        match self.command.deref() {
+            Cmd::Workspaces(icons) => {
+                let list = compositor::workspaces(output).ok();
+                let (text, state) = match &list {
+                    Some(list) => (
+                        workspaces::format_workspaces(icons, list),
+                        workspaces::workspaces_state(list),
+                    ),
+                    None => (config.config.unkown.clone(), None),
+                };
+                let selector = state_selector(&config.css, "workspaces", state);
+                // Urgency doesn't always change the icons, so the selector is compared too
+                let key: Box<str> = format!("{selector} {text}").into();
+                if !config_changed && previous == Some(&key) {
+                    return None;
+                }
+
+                let (cache, glyphs) = match list {
+                    Some(list) => workspaces::render(&config.css, &selector, icons, &list),
+                    None => (generic_render(&config.css, &selector, &text), Vec::new()),
+                };
+                Some((key, cache, glyphs))
+            }
+            Cmd::PersistantWorkspaces(settings) => {
+                let output = output.filter(|_| !settings.all_outputs);
+                let slots = persistant_workspaces::slots(settings, output).unwrap_or_default();
+                let text = persistant_workspaces::persistant_workspaces(&slots);
+                if !config_changed && previous == Some(&text) {
+                    return None;
+                }
+
+                let (cache, glyphs) = persistant_workspaces::render(&config.css, settings, &slots);
+                Some((text, cache, glyphs))
+            }
+            Cmd::Taskbar(settings) => {
+                let text = taskbar::taskbar(output);
+                if !config_changed && previous == Some(&text) {
+                    return None;
+                }
+
+                let (cache, glyphs) = taskbar::render(&config.css, settings, output);
+                Some((text, cache, glyphs))
+            }
+            _ => None,
+        }
+    }
+
+    /// `outputs` are names of the outputs bars are on
+    pub fn render(&mut self, config_changed: bool, config: &HotConfig, outputs: &[Box<str>]) {
+        self.render_outputs(config_changed, config, outputs);
+
+        // Workspaces and windows are rendered directly instead of going through the output
+        if let Cmd::Workspaces(_) | Cmd::PersistantWorkspaces(_) | Cmd::Taskbar(_) =
+            self.command.deref()
+        {
+            if let Some((output, cache, glyphs)) = self.render_output(config_changed, config, None)
+            {
+                self.output = output;
+                self.cache = cache;
+                self.glyphs = glyphs;
+            }
+            return;
+        }
+
+        // Mode names aren't known upfront, so they're used as states for selectors, e.g. `mode.resize`
+        let mut mode_name = None;
+        let (output, state) = match self.command.deref() {
+            Cmd::Mode(settings) => match mode::mode_details(settings) {
+                Ok((output, name)) => {
+                    mode_name = name;
+                    (output, None)
+                }
+                Err(_) => (config.config.unkown.clone(), None),
+            },
+            // Text and state come from the same statvfs call
+            Cmd::Disk(settings) => {
+                disk_details(settings).unwrap_or_else(|_| (config.config.unkown.clone(), None))
+            }
+            // Same for the sysfs scan of batteries
+            Cmd::Battery(settings) => battery_details(settings)
+                .map(|(output, state)| {
+                    (
+                        output.unwrap_or_else(|| config.config.unkown.clone()),
+                        state,
+                    )
+                })
+                .unwrap_or_else(|_| (config.config.unkown.clone(), None)),
+            command => (
+                get_command_output(command).unwrap_or_else(|_| config.config.unkown.clone()),
+                get_command_state(command),
+            ),
+        };
+        if output != self.output || state != self.state || config_changed {
+            if output != self.output {
+                self.alerts.check(&output);
+            }
+
             let format = self.format.replace("%s", &output);
             let format = match &self.command.deref() {
+                Cmd::Battery(BatterySettings { status_icons, .. })
+                | Cmd::Media(MediaSettings { status_icons, .. })
+                | Cmd::IdleInhibitor(IdleInhibitorSettings { status_icons, .. })
+                    if state.is_some_and(|state| status_icons.contains_key(state)) =>
+                {
+                    format.replace("%c", &status_icons[state.unwrap()])
+                }
+                Cmd::Audio(AudioSettings {
+                    muted_icon: Some(icon),
+                    ..
+                })
+                | Cmd::Microphone(AudioSettings {
+                    muted_icon: Some(icon),
+                    ..
+                }) if state == Some("muted") => format.replace("%c", icon),
                 Cmd::Battery(BatterySettings { icons, .. })
                 | Cmd::Backlight(BacklightSettings { icons, .. })
                 | Cmd::Audio(AudioSettings { icons, .. })
+                | Cmd::Microphone(AudioSettings { icons, .. })
                     if !icons.is_empty() =>
                 {
                     if let Ok(output) = output.parse::<usize>() {
@@ -140,31 +423,71 @@ impl ModuleData {
                 Cmd::Battery(_) => "battery",
                 Cmd::Backlight(_) => "backlight",
                 Cmd::Audio(_) => "audio",
-                Cmd::WindowTitle => "title",
+                Cmd::Microphone(_) => "microphone",
+                Cmd::Disk(_) => "disk",
+                Cmd::Privacy(_) => "privacy",
+                Cmd::Media(_) => "media",
+                Cmd::Tray(_) => "tray",
+                Cmd::Taskbar(_) => "taskbar",
+                Cmd::KeyboardLayout(_) => "keyboard_layout",
+                Cmd::Mode(_) => "mode",
+                Cmd::Scratchpad(_) => "scratchpad",
+                Cmd::IdleInhibitor(_) => "idle_inhibitor",
+                Cmd::WindowTitle(_) => "title",
                 Cmd::Custom(custom) => &custom.name,
             };
 
+            let selector = match &self.command.deref() {
+                Cmd::Mode(_) => state_selector(&config.css, name, mode_name.as_deref()),
+                _ => state_selector(&config.css, name, state),
+            };
+
+            self.state = state;
             self.cache = match &self.command.deref() {
-                Cmd::PersistantWorkspaces(_) => {
+                Cmd::Tray(settings) => {
+                    self.output = output;
+                    tray::render(settings, config.config.height)
+                }
+                // Hidden while nothing is recording or playing, in the default mode and with an empty scratchpad
+                Cmd::Privacy(_) | Cmd::Media(_) | Cmd::Mode(_) | Cmd::Scratchpad(_)
+                    if output.is_empty() =>
+                {
                     self.output = output;
-                    persistant_workspaces::render(&config.css, &self.output)
+                    DynamicImage::new(0, 0, ColorType::L8)
                 }
                 _ => {
                     self.output = output;
-                    generic_render(&config.css, name, &format)
+                    generic_render(&config.css, &selector, &format)
                 }
             };
         }
     }
 }
 
-fn generic_render(css: &[Style], name: &str, format: &str) -> DynamicImage {
+/// States such as `disk.critical` are only used when the user styled them
+pub fn state_selector(css: &[Style], name: &str, state: Option<&str>) -> String {
+    match state {
+        Some(state)
+            if css
+                .iter()
+                .any(|style| style.selector == format!("{name}.{state}")) =>
+        {
+            format!("{name}.{state}")
+        }
+        _ => name.to_string(),
+    }
+}
+
+pub fn generic_render(css: &[Style], name: &str, format: &str) -> DynamicImage {
+    // Default styles only know the module name, not its states
+    let base = name.split('.').next().unwrap_or(name);
     let img = get_style(css, name, format).unwrap_or_else(|_| {
         let mut css = CSS
             .iter()
-            .find(|a| a.selector == name)
+            .find(|a| a.selector == base)
             .expect(MESSAGE)
             .to_owned();
+        css.selector = name.into();
         css.content.replace(format.into());
         css_image::render(css).expect(MESSAGE)
     });
@@ -179,9 +502,10 @@ fn generic_render(css: &[Style], name: &str, format: &str) -> DynamicImage {
             warn!("Failed to parse {name} module css, using default style");
             let mut css = CSS
                 .iter()
-                .find(|a| a.selector == name)
+                .find(|a| a.selector == base)
                 .expect(MESSAGE)
                 .to_owned();
+            css.selector = name.into();
             css.content.replace(format.into());
             let css = css_image::render(css).expect(MESSAGE);
             image::load_from_memory(css.get(name).expect(MESSAGE)).unwrap()
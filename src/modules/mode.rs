@@ -0,0 +1,35 @@
+use crate::util::compositor;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, sync::Arc};
+
+#[derive(Deserialize, Serialize, PartialEq)]
+pub struct ModeSettings {
+    pub formatting: Arc<str>,
+    // Mode name to the text displayed for it, e.g. "resize" = "󰩨"
+    #[serde(default)]
+    pub modes: HashMap<Box<str>, Box<str>>,
+}
+
+pub fn mode_name() -> anyhow::Result<Box<str>> {
+    compositor::get()?.binding_mode()
+}
+
+/// Empty in the default mode, which hides the module
+pub fn mode(settings: &ModeSettings) -> anyhow::Result<Box<str>> {
+    Ok(mode_details(settings)?.0)
+}
+
+/// Displayed text along with the mode name it's for, no name in the default mode
+pub fn mode_details(settings: &ModeSettings) -> anyhow::Result<(Box<str>, Option<Box<str>>)> {
+    let name = mode_name()?;
+    if &*name == "default" {
+        return Ok(("".into(), None));
+    }
+
+    let text = settings
+        .modes
+        .get(&name)
+        .cloned()
+        .unwrap_or_else(|| name.clone());
+    Ok((text, Some(name)))
+}
@@ -1,6 +1,6 @@
-use crate::{get_style, CSS, MESSAGE};
+use crate::{get_style, util::compositor, CSS, MESSAGE};
 
-use super::workspaces::{hyprland, sway};
+use super::PointerAction;
 use css_image::style::Style;
 use image::DynamicImage;
 use log::warn;
@@ -8,94 +8,169 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
 #[derive(Deserialize, Serialize, PartialEq)]
-pub struct PersistantWorkspacesIcons(#[serde(default)] pub HashMap<Box<str>, Box<str>>);
+pub struct PersistantWorkspacesIcons {
+    #[serde(default = "workspaces")]
+    pub workspaces: PersistantWorkspaces,
+    // Shows the state of workspaces on every output instead of only the one of the bar
+    #[serde(default)]
+    pub all_outputs: bool,
+    // Icons for workspace names and states
+    #[serde(flatten)]
+    pub icons: HashMap<Box<str>, Box<str>>,
+}
 
-pub fn persistant_workspaces(icons: &HashMap<Box<str>, Box<str>>) -> Box<str> {
-    let hyprland_running = std::env::var("HYPRLAND_INSTANCE_SIGNATURE").is_ok();
-    let sway_running = std::env::var("SWAYSOCK").is_ok();
+/// Number of workspaces shown starting at 1 or their names
+#[derive(Deserialize, Serialize, PartialEq)]
+#[serde(untagged)]
+pub enum PersistantWorkspaces {
+    Count(i32),
+    Names(Vec<Box<str>>),
+}
+
+fn workspaces() -> PersistantWorkspaces {
+    PersistantWorkspaces::Count(10)
+}
 
-    let (active_workspace, _) = match (hyprland_running, sway_running) {
-        (true, _) => hyprland(),
-        (_, true) => sway(),
-        _ => unreachable!(), // Workspace listener wont work without sway or hyprland so no way to call this function anyways
+#[derive(Clone, Copy, PartialEq)]
+pub enum State {
+    Active,
+    // Shown on its output but not focused
+    Visible,
+    Urgent,
+    Occupied,
+    Empty,
+}
+
+impl State {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            State::Active => "active",
+            State::Visible => "visible",
+            State::Urgent => "urgent",
+            State::Occupied => "occupied",
+            State::Empty => "empty",
+        }
     }
-    .unwrap();
-
-    (1..=10)
-        .map(|i| {
-            let index = i.to_string().into_boxed_str();
-            let is_active = active_workspace == i;
-            let string = if is_active { "active" } else { "inactive" };
-
-            let mut icon = icons
-                .get(string)
-                .or_else(|| icons.get(&index))
-                .unwrap_or(&index)
-                .trim()
-                .to_string();
-
-            if !is_active && i < active_workspace {
-                icon.push_str("\u{200B}\u{200B}");
-            } else if is_active {
-                icon.push('\u{200B}'); // Do not judge me
-            }
 
-            icon
+    /// Names looked up for icons and CSS selectors, most specific first
+    fn names(&self) -> &'static [&'static str] {
+        match self {
+            State::Active => &["active"],
+            State::Visible => &["visible", "active"],
+            State::Urgent => &["urgent", "inactive"],
+            State::Occupied => &["occupied", "inactive"],
+            State::Empty => &["empty", "inactive"],
+        }
+    }
+}
+
+pub struct Slot {
+    // Set when workspaces are configured by count
+    pub id: Option<i32>,
+    pub name: Box<str>,
+    pub state: State,
+    pub windows: usize,
+}
+
+/// State of every configured workspace on given output
+pub fn slots(
+    settings: &PersistantWorkspacesIcons,
+    output: Option<&str>,
+) -> anyhow::Result<Vec<Slot>> {
+    let workspaces = compositor::workspaces(output)?;
+    let slots = match &settings.workspaces {
+        PersistantWorkspaces::Count(count) => (1..=*count)
+            .map(|id| (Some(id), id.to_string().into_boxed_str()))
+            .collect::<Vec<_>>(),
+        PersistantWorkspaces::Names(names) => {
+            names.iter().map(|name| (None, name.clone())).collect()
+        }
+    };
+
+    Ok(slots
+        .into_iter()
+        .map(|(id, name)| {
+            let workspace = workspaces.iter().find(|workspace| match id {
+                Some(id) => workspace.id == id,
+                None => workspace.name == name,
+            });
+            let state = match workspace {
+                Some(workspace) if workspace.focused => State::Active,
+                Some(workspace) if workspace.visible => State::Visible,
+                Some(workspace) if workspace.urgent => State::Urgent,
+                Some(workspace) if workspace.windows > 0 => State::Occupied,
+                _ => State::Empty,
+            };
+
+            Slot {
+                id,
+                name,
+                state,
+                windows: workspace.map_or(0, |workspace| workspace.windows),
+            }
         })
+        .collect())
+}
+
+/// Describes the slots, used to tell whether anything changed
+pub fn persistant_workspaces(slots: &[Slot]) -> Box<str> {
+    slots
+        .iter()
+        .map(|slot| format!("{}:{}:{}", slot.name, slot.state.as_str(), slot.windows))
         .collect::<Vec<_>>()
         .join(" ")
         .into()
 }
 
-pub fn render(css: &[Style], icons: &str) -> DynamicImage {
-    let hyprland_running = std::env::var("HYPRLAND_INSTANCE_SIGNATURE").is_ok();
-    let sway_running = std::env::var("SWAYSOCK").is_ok();
-
-    let (active_workspace, _) = match (hyprland_running, sway_running) {
-        (true, _) => hyprland(),
-        (_, true) => sway(),
-        _ => unreachable!(), // Workspace listener wont work without sway or hyprland so no way to call this function anyways
-    }
-    .unwrap();
+/// `%n` is replaced with the number of windows on the workspace
+fn icon(icons: &HashMap<Box<str>, Box<str>>, slot: &Slot) -> String {
+    slot.state
+        .names()
+        .iter()
+        .find_map(|name| icons.get(*name))
+        .or_else(|| icons.get(&slot.name))
+        .unwrap_or(&slot.name)
+        .trim()
+        .replace("%n", &slot.windows.to_string())
+}
 
-    let icons = icons.split_whitespace().collect::<Vec<&str>>();
-    let icons = icons
+/// Returns the image and horizontal range of every icon in it
+pub fn render(
+    css: &[Style],
+    settings: &PersistantWorkspacesIcons,
+    slots: &[Slot],
+) -> (DynamicImage, Vec<(i32, i32)>) {
+    let styled = |selector: &str| {
+        css.iter()
+            .any(|a| a.selector == format!("persistant_workspaces#{selector}"))
+    };
+
+    let icons = slots
         .iter()
         .enumerate()
-        .map(|(i, icon)| {
-            let mut name = "persistant_workspaces#".to_string();
-            match active_workspace.checked_sub(1) {
-                Some(active)
-                    if active == i
-                        && css
-                            .iter()
-                            .any(|a| a.selector == "persistant_workspaces#active") =>
-                {
-                    name.push_str("active");
-                }
-                _ if css
-                    .iter()
-                    .any(|a| a.selector == format!("persistant_workspaces#{i}")) =>
-                {
-                    name.push_str(&i.to_string());
-                }
-                _ if css
-                    .iter()
-                    .any(|a| a.selector == "persistant_workspaces#inactive") =>
-                {
-                    name.push_str("inactive")
-                }
-                _ => name.push_str(&i.to_string()),
-            };
-
-            let style = get_style(css, &name, icon).unwrap_or_else(|_| {
+        .map(|(i, slot)| {
+            let icon = icon(&settings.icons, slot);
+            // State selectors like `persistant_workspaces#occupied` come before index ones
+            let index = i.to_string();
+            let selector = slot
+                .state
+                .names()
+                .iter()
+                .copied()
+                .filter(|name| *name != "inactive")
+                .chain([index.as_str(), "inactive"])
+                .find(|selector| styled(selector))
+                .unwrap_or(index.as_str());
+            let name = format!("persistant_workspaces#{selector}");
+
+            let style = get_style(css, &name, &icon).unwrap_or_else(|_| {
                 let mut css = CSS
                     .iter()
                     .find(|a| a.selector == name.as_str())
                     .expect("Style declaration for module persistant_workspaces not found, using default style")
                     .to_owned();
-                css.content.replace(icon.to_string().into_boxed_str());
-                get_style(&vec![css], &name, icon).expect(MESSAGE)
+                css.content.replace(icon.as_str().into());
+                get_style(&vec![css], &name, &icon).expect(MESSAGE)
             });
             let img_data = style.get(&*name).unwrap();
             image::load_from_memory(img_data).unwrap().to_rgba8()
@@ -119,7 +194,7 @@ pub fn render(css: &[Style], icons: &str) -> DynamicImage {
 
     let img_height = icons.iter().map(|icon| icon.height()).max().unwrap_or(10) as i32;
     let img_width = icons.iter().map(|icon| icon.width() as i32).sum::<i32>()
-        + letter_spacing as i32 * (icons.len() as i32 - 1);
+        + letter_spacing as i32 * icons.len().saturating_sub(1) as i32;
 
     persistant_workspaces.width.replace(img_width);
     persistant_workspaces.height.replace(img_height - 10);
@@ -131,9 +206,46 @@ pub fn render(css: &[Style], icons: &str) -> DynamicImage {
     let mut img = image::load_from_memory(img.get("persistant_workspaces").unwrap())
         .unwrap()
         .to_rgba8();
-    icons.iter().for_each(|icon| {
-        image::imageops::overlay(&mut img, icon, x as i64, y as i64 - 10);
-        x += icon.width() as i32 + letter_spacing as i32;
-    });
-    DynamicImage::from(img)
+    let glyphs = icons
+        .iter()
+        .map(|icon| {
+            image::imageops::overlay(&mut img, icon, x as i64, y as i64 - 10);
+            let start = x;
+            x += icon.width() as i32 + letter_spacing as i32;
+            (start, x)
+        })
+        .collect();
+    (DynamicImage::from(img), glyphs)
+}
+
+/// Clicking an icon switches to its workspace, scrolling cycles through workspaces of the output
+pub fn handle_input(
+    settings: &PersistantWorkspacesIcons,
+    action: PointerAction,
+    x: i32,
+    glyphs: &[(i32, i32)],
+    output: Option<&str>,
+) -> anyhow::Result<()> {
+    match action {
+        PointerAction::LeftClick => {
+            let Some(i) = glyphs
+                .iter()
+                .position(|(start, end)| (*start..*end).contains(&x))
+            else {
+                return Ok(());
+            };
+
+            match slots(settings, output)?.get(i) {
+                Some(Slot { id: Some(id), .. }) => match output {
+                    Some(output) => compositor::get()?.switch_workspace_on(*id, output),
+                    None => compositor::get()?.switch_workspace(*id),
+                },
+                Some(slot) => compositor::get()?.switch_workspace_name(&slot.name),
+                None => Ok(()),
+            }
+        }
+        PointerAction::ScrollUp => compositor::cycle_workspace(output, false),
+        PointerAction::ScrollDown => compositor::cycle_workspace(output, true),
+        _ => Ok(()),
+    }
 }
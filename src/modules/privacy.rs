@@ -0,0 +1,23 @@
+use crate::util::audio::recording;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+#[derive(Deserialize, Serialize, PartialEq)]
+pub struct PrivacySettings {
+    pub formatting: Arc<str>,
+    #[serde(default = "separator")]
+    pub separator: Box<str>,
+}
+
+fn separator() -> Box<str> {
+    ", ".into()
+}
+
+/// Names of applications using the microphone, empty when nothing is recording
+pub fn privacy(settings: &PrivacySettings) -> anyhow::Result<Box<str>> {
+    let mut names = recording()?;
+    names.sort();
+    names.dedup();
+
+    Ok(names.join(&settings.separator).into())
+}
@@ -0,0 +1,30 @@
+use super::PointerAction;
+use crate::util::compositor;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+#[derive(Deserialize, Serialize, PartialEq)]
+pub struct ScratchpadSettings {
+    pub formatting: Arc<str>,
+    #[serde(default)]
+    pub show_on_click: bool,
+}
+
+pub fn scratchpad_windows() -> anyhow::Result<usize> {
+    compositor::get()?.scratchpad()
+}
+
+/// Empty without any windows, which hides the module
+pub fn scratchpad() -> anyhow::Result<Box<str>> {
+    Ok(match scratchpad_windows()? {
+        0 => "".into(),
+        windows => windows.to_string().into(),
+    })
+}
+
+pub fn handle_input(settings: &ScratchpadSettings, action: PointerAction) -> anyhow::Result<()> {
+    match action {
+        PointerAction::LeftClick if settings.show_on_click => compositor::get()?.show_scratchpad(),
+        _ => Ok(()),
+    }
+}
@@ -0,0 +1,160 @@
+use super::{generic_render, state_selector, PointerAction};
+use crate::{
+    get_style,
+    util::{
+        helpers::{combine_images, truncate},
+        icons::load_icon,
+        toplevel::{self, Toplevel},
+    },
+    CSS, MESSAGE,
+};
+use css_image::style::Style;
+use image::{imageops, DynamicImage};
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize, Serialize, PartialEq)]
+pub struct TaskbarSettings {
+    #[serde(default = "enabled")]
+    pub icons: bool,
+    #[serde(default)]
+    pub titles: bool,
+    #[serde(default)]
+    pub max_length: Option<usize>,
+    #[serde(default = "icon_size")]
+    pub icon_size: u32,
+    // Space between the icon and the title
+    #[serde(default = "spacing")]
+    pub spacing: u32,
+    #[serde(default)]
+    pub all_outputs: bool,
+}
+
+fn enabled() -> bool {
+    true
+}
+
+fn icon_size() -> u32 {
+    20
+}
+
+fn spacing() -> u32 {
+    5
+}
+
+/// Changes whenever a window appears, disappears or changes
+pub fn taskbar(output: Option<&str>) -> Box<str> {
+    toplevel::toplevels(output)
+        .iter()
+        .map(|toplevel| {
+            format!(
+                "{}:{}:{}",
+                toplevel.app_id,
+                toplevel.title,
+                toplevel.state().unwrap_or_default()
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+        .into()
+}
+
+fn icon(app_id: &str, size: u32) -> Option<DynamicImage> {
+    let lowercase = app_id.to_lowercase();
+    // Reverse domain app ids like `org.gnome.Nautilus` often name their icon after the last part only
+    let last = lowercase.rsplit('.').next().unwrap_or_default();
+    [app_id, &lowercase, last]
+        .iter()
+        .find_map(|name| load_icon(name, size))
+}
+
+/// Icon is drawn inside the left padding, which is widened to fit it
+fn render_entry(css: &[Style], settings: &TaskbarSettings, toplevel: &Toplevel) -> DynamicImage {
+    let selector = state_selector(css, "taskbar", toplevel.state());
+    let title = match settings.titles {
+        true => truncate(&toplevel.title, settings.max_length),
+        false => String::new(),
+    };
+    let icon = match settings.icons {
+        true => icon(&toplevel.app_id, settings.icon_size),
+        false => None,
+    };
+
+    let mut style = css
+        .iter()
+        .find(|style| style.selector == selector)
+        .unwrap_or_else(|| {
+            CSS.iter()
+                .find(|style| style.selector == "taskbar")
+                .expect(MESSAGE)
+        })
+        .clone();
+    style.selector = selector.as_str().into();
+    let left = style.margin[3] + style.padding[3];
+    if icon.is_some() {
+        style.padding[3] += settings.icon_size as i32;
+        if settings.titles {
+            style.padding[3] += settings.spacing as i32;
+        }
+    }
+
+    let mut image = get_style(&vec![style], &selector, &title)
+        .ok()
+        .and_then(|img| image::load_from_memory(img.get(&selector)?).ok())
+        .unwrap_or_else(|| generic_render(css, &selector, &title));
+    if let Some(icon) = icon {
+        let y = (image.height() as i64 - icon.height() as i64) / 2;
+        imageops::overlay(&mut image, &icon, left as i64, y);
+    }
+
+    image
+}
+
+/// Returns the image and horizontal range of every window in it
+pub fn render(
+    css: &[Style],
+    settings: &TaskbarSettings,
+    output: Option<&str>,
+) -> (DynamicImage, Vec<(i32, i32)>) {
+    let entries = toplevel::toplevels(output)
+        .iter()
+        .map(|toplevel| render_entry(css, settings, toplevel))
+        .collect::<Vec<_>>();
+    let glyphs = entries
+        .iter()
+        .scan(0, |x, entry| {
+            let start = *x;
+            *x += entry.width() as i32;
+            Some((start, *x))
+        })
+        .collect();
+
+    (combine_images(&entries.iter().collect::<Vec<_>>()), glyphs)
+}
+
+/// Clicking activates a window or minimizes it when it's already active,
+/// right click toggles minimizing and middle click closes it
+pub fn handle_input(
+    action: PointerAction,
+    x: i32,
+    glyphs: &[(i32, i32)],
+    output: Option<&str>,
+) -> anyhow::Result<()> {
+    let Some(toplevel) = glyphs
+        .iter()
+        .position(|(start, end)| (*start..*end).contains(&x))
+        .and_then(|i| toplevel::toplevels(output).into_iter().nth(i))
+    else {
+        return Ok(());
+    };
+
+    match action {
+        PointerAction::LeftClick if toplevel.activated => toplevel.handle.set_minimized(),
+        PointerAction::LeftClick => toplevel.activate()?,
+        PointerAction::RightClick if toplevel.minimized => toplevel.handle.unset_minimized(),
+        PointerAction::RightClick => toplevel.handle.set_minimized(),
+        PointerAction::MiddleClick => toplevel.handle.close(),
+        _ => {}
+    }
+
+    Ok(())
+}
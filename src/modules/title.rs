@@ -1,28 +1,121 @@
-use hyprland::shared::HyprDataActiveOptional;
-
-pub fn get_window_title() -> Option<Box<str>> {
-    let hyprland_running = std::env::var("HYPRLAND_INSTANCE_SIGNATURE").is_ok();
-    let sway_running = std::env::var("SWAYSOCK").is_ok();
-
-    match (hyprland_running, sway_running) {
-        (true, _) => Some(
-            hyprland::data::Client::get_active()
-                .ok()??
-                .initial_title
-                .into(),
-        ),
-        (_, true) => {
-            let mut workspaces = swayipc::Connection::new().ok()?.get_workspaces().ok()?;
-            let active_workspace = workspaces.iter_mut().find_map(|workspace| {
-                if workspace.focused {
-                    workspace.representation.take()
-                } else {
-                    None
-                }
-            })?;
-
-            Some(active_workspace.replace(']', "").replace("H[", "").into())
+use crate::util::{compositor, helpers::truncate};
+use regex::Regex;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::sync::Arc;
+
+#[derive(Deserialize, Serialize, PartialEq)]
+#[serde(default)]
+pub struct TitleSettings {
+    // "%s" is replaced with the title and "%a" with the app_id
+    pub formatting: Arc<str>,
+    // Titles longer than this are cut off with an ellipsis
+    pub max_length: Option<usize>,
+    pub rewrite: Vec<RewriteRule>,
+}
+
+impl Default for TitleSettings {
+    fn default() -> Self {
+        Self {
+            formatting: "%s".into(),
+            max_length: None,
+            rewrite: Vec::new(),
         }
-        _ => None,
+    }
+}
+
+/// Replaces titles matching `pattern`, `$1` and alike refer to its capture groups
+#[derive(Deserialize, Serialize, PartialEq)]
+pub struct RewriteRule {
+    // Only applies to windows with this app_id when set
+    #[serde(default)]
+    pub app_id: Option<Box<str>>,
+    pub pattern: Pattern,
+    pub replacement: Box<str>,
+}
+
+/// Compiled when the config is loaded, so invalid patterns are rejected along with it
+pub struct Pattern(Regex);
+
+impl PartialEq for Pattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.as_str() == other.0.as_str()
+    }
+}
+
+impl Serialize for Pattern {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.0.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for Pattern {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let pattern = String::deserialize(deserializer)?;
+        Regex::new(&pattern)
+            .map(Pattern)
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+fn rewrite(rules: &[RewriteRule], app_id: &str, title: &str) -> String {
+    rules
+        .iter()
+        .filter(|rule| rule.app_id.as_deref().map_or(true, |id| id == app_id))
+        .find(|rule| rule.pattern.0.is_match(title))
+        .map_or_else(
+            || title.to_string(),
+            |rule| {
+                rule.pattern
+                    .0
+                    .replace(title, &*rule.replacement)
+                    .into_owned()
+            },
+        )
+}
+
+pub fn get_window_title(settings: &TitleSettings) -> anyhow::Result<Box<str>> {
+    let Some(window) = compositor::get()?.focused_window()? else {
+        return Ok("".into());
+    };
+
+    let title = truncate(
+        &rewrite(&settings.rewrite, &window.app_id, &window.title),
+        settings.max_length,
+    );
+
+    Ok(settings
+        .formatting
+        .replace("%s", &title)
+        .replace("%a", &window.app_id)
+        .into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rewrite_rules() {
+        let settings = toml::from_str::<TitleSettings>(
+            r#"rewrite = [{ app_id = "firefox", pattern = "(.*) — Mozilla Firefox", replacement = "$1" }]"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            rewrite(&settings.rewrite, "firefox", "Docs — Mozilla Firefox"),
+            "Docs"
+        );
+        assert_eq!(
+            rewrite(&settings.rewrite, "foot", "Docs — Mozilla Firefox"),
+            "Docs — Mozilla Firefox"
+        );
+    }
+
+    #[test]
+    fn invalid_pattern() {
+        assert!(toml::from_str::<TitleSettings>(
+            r#"rewrite = [{ pattern = "(unclosed", replacement = "" }]"#
+        )
+        .is_err());
     }
 }
@@ -0,0 +1,76 @@
+use super::PointerAction;
+use crate::util::tray::{call, items, Item};
+use image::{imageops, DynamicImage};
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize, Serialize, PartialEq)]
+pub struct TraySettings {
+    #[serde(default = "icon_size")]
+    pub icon_size: u32,
+    #[serde(default = "spacing")]
+    pub spacing: u32,
+}
+
+fn icon_size() -> u32 {
+    20
+}
+
+fn spacing() -> u32 {
+    10
+}
+
+/// Changes whenever an item appears, disappears or changes its icon
+pub fn tray_details() -> Box<str> {
+    items()
+        .iter()
+        .map(|item| item.key())
+        .collect::<Vec<_>>()
+        .join(" ")
+        .into()
+}
+
+/// Icons are centered vertically in the bar with spacing before each one
+pub fn render(settings: &TraySettings, height: i32) -> DynamicImage {
+    let items = items();
+    let slot = settings.icon_size + settings.spacing;
+    let height = (height as u32).max(settings.icon_size);
+    let mut image = DynamicImage::new_rgba8(slot * items.len() as u32, height);
+
+    items.iter().enumerate().for_each(|(i, item)| {
+        if let Some(icon) = item.icon(settings.icon_size) {
+            imageops::overlay(
+                &mut image,
+                &icon,
+                (i as u32 * slot + settings.spacing) as i64,
+                ((height - settings.icon_size) / 2) as i64,
+            );
+        }
+    });
+
+    image
+}
+
+fn item_at(settings: &TraySettings, x: i32) -> Option<Item> {
+    let slot = (settings.icon_size + settings.spacing) as i32;
+    items().into_iter().nth((x / slot) as usize)
+}
+
+/// Destination and object path of the menu of the item under given position
+pub fn menu_at(settings: &TraySettings, x: i32) -> Option<(Box<str>, Box<str>)> {
+    item_at(settings, x)?.menu()
+}
+
+pub fn handle_input(settings: &TraySettings, action: PointerAction, x: i32) -> anyhow::Result<()> {
+    let Some(item) = item_at(settings, x) else {
+        return Ok(());
+    };
+
+    match action {
+        PointerAction::LeftClick => call(&item, "Activate", &(0i32, 0i32)),
+        PointerAction::MiddleClick => call(&item, "SecondaryActivate", &(0i32, 0i32)),
+        // Items with a menu have it opened as a popup instead
+        PointerAction::RightClick => call(&item, "ContextMenu", &(0i32, 0i32)),
+        PointerAction::ScrollUp => call(&item, "Scroll", &(-1i32, "vertical")),
+        PointerAction::ScrollDown => call(&item, "Scroll", &(1i32, "vertical")),
+    }
+}
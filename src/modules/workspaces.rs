@@ -1,62 +1,194 @@
-use hyprland::shared::HyprData;
-use hyprland::shared::HyprDataActive;
+use super::{generic_render, PointerAction};
+use crate::util::compositor::{self, Workspace};
+use css_image::style::Style;
+use image::DynamicImage;
 use serde::Deserialize;
 use serde::Serialize;
+use std::collections::HashMap;
 
 #[derive(Deserialize, Serialize, PartialEq)]
 pub struct WorkspacesIcons {
     pub active: Box<str>,
     pub inactive: Box<str>,
+    // Shown on another output, same as active by default
+    #[serde(default)]
+    pub visible: Option<Box<str>>,
+    // Inactive workspace with an urgent window, same as inactive by default
+    #[serde(default)]
+    pub urgent: Option<Box<str>>,
+    #[serde(default)]
+    pub all_outputs: bool,
+    #[serde(default = "output_separator")]
+    pub output_separator: Box<str>,
 }
 
-pub fn workspaces(workspace: &WorkspacesIcons) -> Box<str> {
-    let hyprland_running = std::env::var("HYPRLAND_INSTANCE_SIGNATURE").is_ok();
-    let sway_running = std::env::var("SWAYSOCK").is_ok();
+fn output_separator() -> Box<str> {
+    "| ".into()
+}
 
-    let (active_workspace, length) = match (hyprland_running, sway_running) {
-        (true, _) => hyprland(),
-        (_, true) => sway(),
-        _ => unreachable!(), // Workspace listener wont work without sway or hyprland so no way to call this function anyways
-    }
-    .unwrap();
-
-    (0..length)
-        .fold(String::new(), |mut workspace_state, i| {
-            let workspace =
-                if i == active_workspace - 1 || i == length - 1 && active_workspace > length {
-                    &workspace.active
-                } else {
-                    &workspace.inactive
-                };
-            workspace_state.push_str(workspace);
-            workspace_state.push(' ');
-
-            workspace_state
+/// Workspaces on given output, or all of them grouped by output when it's `None`
+pub fn workspaces(icons: &WorkspacesIcons, output: Option<&str>) -> anyhow::Result<Box<str>> {
+    Ok(format_workspaces(icons, &compositor::workspaces(output)?))
+}
+
+/// `%n` in icons is replaced with the number of windows on the workspace
+pub fn format_workspaces(icons: &WorkspacesIcons, workspaces: &[Workspace]) -> Box<str> {
+    segments(icons, workspaces).concat().into()
+}
+
+/// Text of every workspace, the output separator is part of the first workspace of an output
+fn segments(icons: &WorkspacesIcons, workspaces: &[Workspace]) -> Vec<String> {
+    workspaces
+        .iter()
+        .enumerate()
+        .map(|(i, workspace)| {
+            let icon = match (workspace.focused, workspace.visible, workspace.urgent) {
+                (true, _, _) => &icons.active,
+                (false, true, _) => icons.visible.as_ref().unwrap_or(&icons.active),
+                (false, false, true) => icons.urgent.as_ref().unwrap_or(&icons.inactive),
+                (false, false, false) => &icons.inactive,
+            };
+            let separator = match i > 0 && workspaces[i - 1].output != workspace.output {
+                true => &*icons.output_separator,
+                false => "",
+            };
+
+            format!(
+                "{separator}{} ",
+                icon.replace("%n", &workspace.windows.to_string())
+            )
         })
-        .into()
+        .collect()
 }
 
-pub fn hyprland() -> anyhow::Result<(usize, usize)> {
-    let active_workspace = hyprland::data::Workspace::get_active()?.id as usize;
-    let length = hyprland::data::Workspaces::get()?.iter().count();
+/// `urgent` while a workspace that isn't focused has an urgent window
+pub fn workspaces_state(workspaces: &[Workspace]) -> Option<&'static str> {
+    workspaces
+        .iter()
+        .any(|workspace| workspace.urgent && !workspace.focused)
+        .then_some("urgent")
+}
+
+/// Returns the image and horizontal range of every workspace in it,
+/// icons can differ in width so every distinct one is measured by rendering it on its own
+pub fn render(
+    css: &[Style],
+    selector: &str,
+    icons: &WorkspacesIcons,
+    workspaces: &[Workspace],
+) -> (DynamicImage, Vec<(i32, i32)>) {
+    let segments = segments(icons, workspaces);
+    let image = generic_render(css, selector, &segments.concat());
+
+    let mut measured = HashMap::new();
+    let widths = segments
+        .iter()
+        .map(|segment| {
+            *measured
+                .entry(segment.as_str())
+                .or_insert_with(|| generic_render(css, selector, segment).width() as i32)
+        })
+        .collect::<Vec<_>>();
 
-    Ok((active_workspace, length))
+    let ranges = glyphs(&widths, image.width() as i32);
+    (image, ranges)
 }
 
-pub fn sway() -> anyhow::Result<(usize, usize)> {
-    let workspaces = swayipc::Connection::new()?.get_workspaces()?;
+/// Ranges of segments measured with padding, laid out in an image `width` wide.
+/// The padding every measurement has in excess is what is left to the outer ranges
+fn glyphs(widths: &[i32], width: i32) -> Vec<(i32, i32)> {
+    let padding = match widths.len() {
+        0 | 1 => 0,
+        n => (widths.iter().sum::<i32>() - width) / (n as i32 - 1),
+    };
 
-    let active_workspace = workspaces
+    let mut end = padding / 2;
+    let mut glyphs = widths
         .iter()
-        .enumerate()
-        .find_map(|(i, workspace)| {
-            if workspace.focused {
-                Some(workspaces[i].num as usize) // What?
-            } else {
-                None
-            }
+        .map(|segment| {
+            let start = end;
+            end += segment - padding;
+            (start, end)
         })
-        .ok_or_else(|| anyhow::anyhow!("Focused workspace not found"))?;
+        .collect::<Vec<_>>();
+    if let Some(first) = glyphs.first_mut() {
+        first.0 = 0;
+    }
+    if let Some(last) = glyphs.last_mut() {
+        last.1 = width;
+    }
+
+    glyphs
+}
+
+/// Clicking a glyph switches to its workspace, scrolling cycles through workspaces of the output
+pub fn handle_input(
+    action: PointerAction,
+    x: i32,
+    glyphs: &[(i32, i32)],
+    output: Option<&str>,
+) -> anyhow::Result<()> {
+    match action {
+        PointerAction::LeftClick => {
+            let Some(i) = glyphs
+                .iter()
+                .position(|(start, end)| (*start..*end).contains(&x))
+            else {
+                return Ok(());
+            };
+
+            match compositor::workspaces(output)?.get(i) {
+                Some(workspace) => compositor::switch_to(workspace),
+                None => Ok(()),
+            }
+        }
+        PointerAction::ScrollUp => compositor::cycle_workspace(output, false),
+        PointerAction::ScrollDown => compositor::cycle_workspace(output, true),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn workspace(output: &str, focused: bool, windows: usize) -> Workspace {
+        Workspace {
+            id: 1,
+            name: "1".into(),
+            output: output.into(),
+            focused,
+            visible: focused,
+            urgent: false,
+            windows,
+        }
+    }
 
-    Ok((active_workspace, workspaces.len()))
+    #[test]
+    fn separator_starts_output() {
+        let icons = WorkspacesIcons {
+            active: "%n".into(),
+            inactive: "o".into(),
+            visible: None,
+            urgent: None,
+            all_outputs: true,
+            output_separator: output_separator(),
+        };
+        let workspaces = [
+            workspace("DP-1", true, 2),
+            workspace("DP-1", false, 0),
+            workspace("HDMI-A-1", false, 1),
+        ];
+
+        assert_eq!(segments(&icons, &workspaces), ["2 ", "o ", "| o "]);
+        assert_eq!(&*format_workspaces(&icons, &workspaces), "2 o | o ");
+    }
+
+    #[test]
+    fn glyph_ranges() {
+        // 10 pixels of padding around segments 20, 20 and 30 wide
+        assert_eq!(glyphs(&[30, 30, 40], 80), [(0, 25), (25, 45), (45, 80)]);
+        assert_eq!(glyphs(&[30], 30), [(0, 30)]);
+        assert!(glyphs(&[], 10).is_empty());
+    }
 }
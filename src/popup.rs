@@ -0,0 +1,168 @@
+use crate::{
+    menu::Menu, mixer::Mixer, modules::PointerAction, surface::Surface, HotConfig, StatusBar,
+};
+use image::{imageops, DynamicImage, ImageBuffer, Rgba};
+use smithay_client_toolkit::{
+    compositor::CompositorState,
+    shell::{
+        wlr_layer::{Anchor, KeyboardInteractivity, Layer, LayerShell, LayerSurface},
+        WaylandSurface,
+    },
+    shm::{slot::SlotPool, Shm},
+};
+use wayland_client::{protocol::wl_shm, QueueHandle};
+
+pub enum Content {
+    Mixer(Mixer),
+    Menu(Menu),
+}
+
+/// Surface shown next to the bar below the module that opened it
+pub struct Popup {
+    pub layer_surface: LayerSurface,
+    configured: bool,
+    width: i32,
+    height: i32,
+    // Horizontal position of the module which opened the popup and width of its output
+    x: i32,
+    output_width: i32,
+    image: DynamicImage,
+    content: Content,
+}
+
+/// Stacks rows on top of each other, returns the image and vertical range of each row
+pub fn stack_rows(config: &HotConfig, rows: &[DynamicImage]) -> (DynamicImage, Vec<(i32, i32)>) {
+    let width = rows.iter().map(|img| img.width()).max().unwrap_or(0);
+    let height = rows.iter().map(|img| img.height()).sum::<u32>();
+    let background = config.config.background;
+    let mut image = DynamicImage::ImageRgba8(ImageBuffer::from_pixel(
+        width,
+        height,
+        Rgba([
+            background[0] as u8,
+            background[1] as u8,
+            background[2] as u8,
+            (background[3] * 255.0) as u8,
+        ]),
+    ));
+
+    let mut top = 0;
+    let ranges = rows
+        .iter()
+        .map(|img| {
+            imageops::overlay(&mut image, img, 0, top as i64);
+            let range = (top, top + img.height() as i32);
+            top = range.1;
+            range
+        })
+        .collect();
+
+    (image, ranges)
+}
+
+impl Popup {
+    pub fn new(
+        compositor_state: &CompositorState,
+        layer_shell: &LayerShell,
+        qh: &QueueHandle<StatusBar>,
+        surface: &Surface,
+        topbar: bool,
+        x: i32,
+        content: Content,
+    ) -> Self {
+        let output_width = surface.width;
+        let layer_surface = layer_shell.create_layer_surface(
+            qh,
+            compositor_state.create_surface(qh),
+            Layer::Overlay,
+            Some(concat!(env!("CARGO_PKG_NAME"), "-popup")),
+            Some(&surface.output),
+        );
+
+        layer_surface.set_anchor(match topbar {
+            true => Anchor::TOP | Anchor::LEFT,
+            false => Anchor::BOTTOM | Anchor::LEFT,
+        });
+        // Exclusive zone of 0 places the popup next to the bar instead of over it
+        layer_surface.set_exclusive_zone(0);
+        layer_surface.set_keyboard_interactivity(KeyboardInteractivity::None);
+
+        Self {
+            layer_surface,
+            configured: false,
+            width: 0,
+            height: 0,
+            x,
+            output_width,
+            image: DynamicImage::new_rgba8(0, 0),
+            content,
+        }
+    }
+
+    pub fn configure(&mut self) {
+        self.configured = true;
+    }
+
+    pub fn render(&mut self, config: &HotConfig) {
+        self.image = match &mut self.content {
+            Content::Mixer(mixer) => mixer.render(config),
+            Content::Menu(menu) => menu.render(config),
+        };
+    }
+
+    /// Handles pointer input, `x` and `y` are relative to the popup. Returns whether the popup
+    /// should be closed
+    pub fn handle_input(&mut self, action: PointerAction, x: f64, y: f64) -> anyhow::Result<bool> {
+        let (x, y) = (x as i32, y as i32);
+        match &mut self.content {
+            Content::Mixer(mixer) => mixer.handle_input(action, x, y).map(|_| false),
+            Content::Menu(menu) => menu.handle_input(action, y),
+        }
+    }
+
+    pub fn draw(&mut self, shm: &Shm) -> anyhow::Result<()> {
+        let (width, height) = (self.image.width() as i32, self.image.height() as i32);
+        if width == 0 || height == 0 {
+            return Ok(());
+        }
+
+        // New size has to be acknowledged by the compositor before drawing
+        if (width, height) != (self.width, self.height) {
+            self.width = width;
+            self.height = height;
+            self.configured = false;
+            let margin = self.x.min(self.output_width - width).max(0);
+            self.layer_surface.set_margin(0, 0, 0, margin);
+            self.layer_surface.set_size(width as u32, height as u32);
+            self.layer_surface.commit();
+            return Ok(());
+        }
+
+        if !self.configured {
+            return Ok(());
+        }
+
+        let mut pool = SlotPool::new((width * height * 4) as usize, shm)?;
+        let (buffer, canvas) =
+            pool.create_buffer(width, height, width * 4, wl_shm::Format::Abgr8888)?;
+
+        self.image
+            .to_rgba8()
+            .chunks_exact(4)
+            .zip(canvas.chunks_exact_mut(4))
+            .for_each(|(pixel, canvas)| {
+                let alpha = pixel[3] as f32 / 255.0;
+                canvas[0] = (pixel[0] as f32 * alpha) as u8;
+                canvas[1] = (pixel[1] as f32 * alpha) as u8;
+                canvas[2] = (pixel[2] as f32 * alpha) as u8;
+                canvas[3] = pixel[3];
+            });
+
+        let surface = self.layer_surface.wl_surface();
+        surface.damage_buffer(0, 0, width, height);
+        surface.attach(Some(buffer.wl_buffer()), 0, 0);
+        surface.commit();
+
+        Ok(())
+    }
+}
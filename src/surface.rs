@@ -6,12 +6,18 @@ use smithay_client_toolkit::{
     shell::{wlr_layer::LayerSurface, WaylandSurface},
     shm::slot::Buffer,
 };
+use wayland_client::protocol::wl_output;
+use wayland_protocols::wp::idle_inhibit::zv1::client::zwp_idle_inhibitor_v1::ZwpIdleInhibitorV1;
 
 pub struct Surface {
+    pub output: wl_output::WlOutput,
     pub output_info: OutputInfo,
     pub layer_surface: LayerSurface,
     pub width: i32,
     pub background: DynamicImage,
+    // Module index and horizontal range it was drawn at, used to route pointer input
+    pub hit_boxes: Vec<(usize, i32, i32)>,
+    pub idle_inhibitor: Option<ZwpIdleInhibitorV1>,
 }
 
 impl Surface {
@@ -26,10 +32,10 @@ impl Surface {
         let width = self.width;
         let height = config.height;
 
-        let (left_imgs, center_imgs, mut right_imgs) = module_info.iter().fold(
+        let (left_imgs, center_imgs, mut right_imgs) = module_info.iter().enumerate().fold(
             (Vec::new(), Vec::new(), Vec::new()),
-            |(mut left_imgs, mut center_imgs, mut right_imgs), info| {
-                let img = &info.cache;
+            |(mut left_imgs, mut center_imgs, mut right_imgs), (i, info)| {
+                let img = (i, info.cache(self.output_info.name.as_deref()));
                 match info.position.as_ref() {
                     Position::Left => left_imgs.push(img),
                     Position::Center => center_imgs.push(img),
@@ -40,9 +46,27 @@ impl Surface {
         );
         right_imgs.reverse();
 
-        let left = combine_images(&left_imgs);
-        let center = combine_images(&center_imgs);
-        let right = combine_images(&right_imgs);
+        let combine = |imgs: &[(usize, &DynamicImage)]| {
+            combine_images(&imgs.iter().map(|(_, img)| *img).collect::<Vec<_>>())
+        };
+        let left = combine(&left_imgs);
+        let center = combine(&center_imgs);
+        let right = combine(&right_imgs);
+
+        self.hit_boxes = [
+            (&left_imgs, 0),
+            (&center_imgs, width / 2 - center.width() as i32 / 2),
+            (&right_imgs, width - right.width() as i32),
+        ]
+        .iter()
+        .flat_map(|(imgs, start)| {
+            imgs.iter().scan(*start, |x, (i, img)| {
+                let hit_box = (*i, *x, *x + img.width() as i32);
+                *x += img.width() as i32;
+                Some(hit_box)
+            })
+        })
+        .collect();
 
         let mut background = self.background.clone(); // Can't overwrite the background so we clone it
         imageops::overlay(
@@ -106,6 +130,15 @@ impl Surface {
         }
     }
 
+    /// Returns index of the module under given position and position relative to the module
+    pub fn module_at(&self, x: f64) -> Option<(usize, i32)> {
+        let x = x as i32;
+        self.hit_boxes
+            .iter()
+            .find(|(_, start, end)| (*start..*end).contains(&x))
+            .map(|(i, start, _)| (*i, x - start))
+    }
+
     pub fn is_configured(&self) -> bool {
         self.width != 0
     }
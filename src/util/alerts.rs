@@ -0,0 +1,228 @@
+use super::dbus::with_session;
+use crate::modules::custom::new_command;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+use zbus::{blocking::Connection, zvariant::Value};
+
+#[derive(Deserialize, Serialize, PartialEq)]
+pub struct Alert {
+    pub condition: Condition,
+    pub summary: Box<str>,
+    #[serde(default)]
+    pub body: Box<str>,
+    #[serde(default)]
+    pub urgency: Urgency,
+    #[serde(default = "cooldown")]
+    pub cooldown: u64,
+    #[serde(default)]
+    pub command: Option<Box<str>>,
+}
+
+fn cooldown() -> u64 {
+    300000
+}
+
+#[derive(Deserialize, Serialize, PartialEq)]
+pub enum Condition {
+    Above(f64),
+    Below(f64),
+    Equal(f64),
+}
+
+impl Condition {
+    fn matches(&self, value: f64) -> bool {
+        match self {
+            Condition::Above(threshold) => value > *threshold,
+            Condition::Below(threshold) => value < *threshold,
+            Condition::Equal(threshold) => value == *threshold,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Default, Clone, Copy)]
+pub enum Urgency {
+    Low,
+    #[default]
+    Normal,
+    Critical,
+}
+
+#[derive(Default)]
+struct AlertState {
+    last_sent: Option<Instant>,
+    // Notification id, reused so repeated alerts replace each other instead of piling up
+    id: Arc<Mutex<u32>>,
+}
+
+pub struct Alerts {
+    alerts: Arc<[Alert]>,
+    state: Vec<AlertState>,
+}
+
+impl Alerts {
+    pub fn new(alerts: Arc<[Alert]>) -> Self {
+        let state = alerts.iter().map(|_| AlertState::default()).collect();
+        Self { alerts, state }
+    }
+
+    /// Checks the module output against all rules and sends the ones which match and aren't cooling down
+    pub fn check(&mut self, output: &str) {
+        let Ok(value) = output.trim().parse::<f64>() else {
+            return;
+        };
+
+        self.alerts
+            .iter()
+            .zip(self.state.iter_mut())
+            .enumerate()
+            .filter(|(_, (alert, state))| {
+                alert.condition.matches(value)
+                    && state.last_sent.map_or(true, |last_sent| {
+                        last_sent.elapsed() >= Duration::from_millis(alert.cooldown)
+                    })
+            })
+            .for_each(|(i, (alert, state))| {
+                state.last_sent = Some(Instant::now());
+
+                let alerts = self.alerts.clone();
+                let id = state.id.clone();
+                let output: Box<str> = output.into();
+                thread::spawn(move || {
+                    let alert = &alerts[i];
+                    if let Err(e) = send(alert, &output, &id) {
+                        warn!("Failed to send alert '{}'\n {}", alert.summary, e);
+                    }
+                });
+            });
+    }
+}
+
+fn send(alert: &Alert, output: &str, id: &Mutex<u32>) -> anyhow::Result<()> {
+    let summary = alert.summary.replace("%s", output);
+    let body = alert.body.replace("%s", output);
+
+    if let Some(command) = &alert.command {
+        new_command(
+            &command
+                .replace("%s", output)
+                .replace("%u", &summary)
+                .replace("%b", &body),
+        )?;
+        return Ok(());
+    }
+
+    with_session(|connection| {
+        let mut id = id.lock().unwrap();
+        *id = notify(connection, &summary, &body, alert.urgency, *id)?;
+        Ok(())
+    })
+}
+
+/// Sends a notification through org.freedesktop.Notifications and returns its id
+pub fn notify(
+    connection: &Connection,
+    summary: &str,
+    body: &str,
+    urgency: Urgency,
+    replaces_id: u32,
+) -> zbus::Result<u32> {
+    let mut hints = HashMap::new();
+    hints.insert("urgency", Value::U8(urgency as u8));
+
+    connection
+        .call_method(
+            Some("org.freedesktop.Notifications"),
+            "/org/freedesktop/Notifications",
+            Some("org.freedesktop.Notifications"),
+            "Notify",
+            &(
+                env!("CARGO_PKG_NAME"),
+                replaces_id,
+                "",
+                summary,
+                body,
+                Vec::<&str>::new(),
+                hints,
+                -1,
+            ),
+        )?
+        .body()
+        .deserialize::<u32>()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::dbus::peer_to_peer;
+    use zbus::{interface, zvariant::OwnedValue};
+
+    // Stands in for a notification daemon, new notifications get increasing ids
+    #[derive(Default)]
+    struct NotificationServer {
+        received: Arc<Mutex<Vec<(u32, String, u8)>>>,
+    }
+
+    #[interface(name = "org.freedesktop.Notifications")]
+    impl NotificationServer {
+        #[allow(clippy::too_many_arguments)]
+        fn notify(
+            &self,
+            _app_name: &str,
+            replaces_id: u32,
+            _app_icon: &str,
+            summary: &str,
+            _body: &str,
+            _actions: Vec<String>,
+            hints: HashMap<String, OwnedValue>,
+            _expire_timeout: i32,
+        ) -> u32 {
+            let urgency = hints
+                .get("urgency")
+                .and_then(|urgency| u8::try_from(urgency).ok())
+                .unwrap_or_default();
+            let mut received = self.received.lock().unwrap();
+            received.push((replaces_id, summary.to_string(), urgency));
+            match replaces_id {
+                0 => received.len() as u32,
+                id => id,
+            }
+        }
+    }
+
+    #[test]
+    fn notify_replaces_previous_notification() {
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let (client, _server) = peer_to_peer(
+            "/org/freedesktop/Notifications",
+            NotificationServer {
+                received: received.clone(),
+            },
+        );
+
+        let id = notify(&client, "Battery low", "", Urgency::Critical, 0).unwrap();
+        let replaced = notify(&client, "Battery low", "", Urgency::Critical, id).unwrap();
+
+        assert_eq!(id, replaced);
+        assert_eq!(
+            *received.lock().unwrap(),
+            [
+                (0, "Battery low".to_string(), 2),
+                (id, "Battery low".to_string(), 2)
+            ]
+        );
+    }
+
+    #[test]
+    fn conditions() {
+        assert!(Condition::Above(80.0).matches(81.0));
+        assert!(!Condition::Above(80.0).matches(80.0));
+        assert!(Condition::Below(20.0).matches(19.5));
+        assert!(Condition::Equal(100.0).matches(100.0));
+    }
+}
@@ -0,0 +1,131 @@
+use super::{pipewire::PipeWireBackend, pulse::PulseBackend};
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+use tokio::sync::broadcast;
+
+static BACKEND: OnceLock<Box<dyn AudioBackend + Send + Sync>> = OnceLock::new();
+
+#[derive(Deserialize, Serialize, PartialEq, Default, Clone, Copy)]
+pub enum Backend {
+    #[default]
+    Auto,
+    Pulse,
+    PipeWire,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Device {
+    Sink,
+    Source,
+}
+
+pub struct DeviceInfo {
+    pub volume: u8,
+    pub muted: bool,
+    pub description: Box<str>,
+    pub port: Option<Box<str>>,
+}
+
+/// Audio stream of an application
+#[derive(Clone)]
+pub struct SinkInput {
+    pub index: u32,
+    pub name: Box<str>,
+    pub icon: Option<Box<str>>,
+    pub volume: u8,
+    pub muted: bool,
+    pub sink: u32,
+}
+
+pub struct Sink {
+    pub index: u32,
+    pub description: Box<str>,
+}
+
+pub trait AudioBackend {
+    fn default_device(&self, device: Device) -> anyhow::Result<DeviceInfo>;
+
+    /// Names of applications currently recording audio
+    fn recording(&self) -> anyhow::Result<Vec<Box<str>>>;
+
+    /// Sends to `tx` whenever default devices or their state change, may block for as long as
+    /// anyone is listening
+    fn listen(&self, tx: broadcast::Sender<()>);
+
+    /// Audio streams of applications, listed by the mixer
+    fn sink_inputs(&self) -> anyhow::Result<Vec<SinkInput>>;
+
+    fn sinks(&self) -> anyhow::Result<Vec<Sink>>;
+
+    /// Sets the average volume in percent, capped at 100, keeping the balance between channels
+    fn set_sink_input_volume(&self, index: u32, volume: u8) -> anyhow::Result<()>;
+
+    fn set_sink_input_mute(&self, index: u32, mute: bool) -> anyhow::Result<()>;
+
+    fn move_sink_input(&self, index: u32, sink: u32) -> anyhow::Result<()>;
+}
+
+fn pipewire_running() -> bool {
+    let runtime_dir = std::env::var("XDG_RUNTIME_DIR").unwrap_or_default();
+    let remote = std::env::var("PIPEWIRE_REMOTE").unwrap_or("pipewire-0".into());
+    std::path::Path::new(&runtime_dir).join(remote).exists()
+}
+
+/// Selects the backend, has to be called before any audio module or listener is used
+pub fn init(backend: Backend) {
+    BACKEND.get_or_init(|| {
+        let backend = match backend {
+            Backend::Auto if pipewire_running() => Backend::PipeWire,
+            Backend::Auto => Backend::Pulse,
+            backend => backend,
+        };
+
+        match backend {
+            Backend::PipeWire => {
+                info!("Using PipeWire audio backend");
+                Box::new(PipeWireBackend::new())
+            }
+            _ => {
+                info!("Using PulseAudio audio backend");
+                Box::new(PulseBackend)
+            }
+        }
+    });
+}
+
+fn backend() -> &'static (dyn AudioBackend + Send + Sync) {
+    BACKEND.get_or_init(|| Box::new(PulseBackend)).as_ref()
+}
+
+pub fn default_device(device: Device) -> anyhow::Result<DeviceInfo> {
+    backend().default_device(device)
+}
+
+pub fn recording() -> anyhow::Result<Vec<Box<str>>> {
+    backend().recording()
+}
+
+pub fn listen(tx: broadcast::Sender<()>) {
+    backend().listen(tx)
+}
+
+pub fn sink_inputs() -> anyhow::Result<Vec<SinkInput>> {
+    backend().sink_inputs()
+}
+
+pub fn sinks() -> anyhow::Result<Vec<Sink>> {
+    backend().sinks()
+}
+
+pub fn set_sink_input_volume(index: u32, volume: u8) -> anyhow::Result<()> {
+    backend().set_sink_input_volume(index, volume)
+}
+
+pub fn set_sink_input_mute(index: u32, mute: bool) -> anyhow::Result<()> {
+    backend().set_sink_input_mute(index, mute)
+}
+
+pub fn move_sink_input(index: u32, sink: u32) -> anyhow::Result<()> {
+    backend().move_sink_input(index, sink)
+}
@@ -0,0 +1,1055 @@
+use super::{Compositor, Workspace};
+use log::warn;
+use protocols::{
+    ext_workspace_group_handle_v1::{self, ExtWorkspaceGroupHandleV1},
+    ext_workspace_handle_v1::{self, ExtWorkspaceHandleV1},
+    ext_workspace_manager_v1::{self, ExtWorkspaceManagerV1},
+    zext_workspace_group_handle_v1::{self, ZextWorkspaceGroupHandleV1},
+    zext_workspace_handle_v1::{self, ZextWorkspaceHandleV1},
+    zext_workspace_manager_v1::{self, ZextWorkspaceManagerV1},
+};
+use std::{
+    sync::{Arc, Mutex},
+    thread,
+};
+use tokio::sync::broadcast::{self, error::RecvError};
+use wayland_client::{
+    backend::ObjectId,
+    event_created_child,
+    globals::{registry_queue_init, GlobalListContents},
+    protocol::{wl_output, wl_registry},
+    Connection, Dispatch, Proxy, QueueHandle, WEnum,
+};
+
+// Vendored, wayland-protocols only ships ext-workspace-v1 from 0.32.6 on while sctk uses 0.31,
+// and never shipped the zext draft that came before it
+#[allow(
+    non_upper_case_globals,
+    non_camel_case_types,
+    unused_imports,
+    clippy::all
+)]
+mod protocols {
+    use wayland_client;
+    use wayland_client::protocol::*;
+
+    pub mod __interfaces {
+        use wayland_client::protocol::__interfaces::*;
+        wayland_scanner::generate_interfaces!("protocols/ext-workspace-v1.xml");
+        wayland_scanner::generate_interfaces!("protocols/ext-workspace-unstable-v1.xml");
+    }
+    use self::__interfaces::*;
+
+    wayland_scanner::generate_client_code!("protocols/ext-workspace-v1.xml");
+    wayland_scanner::generate_client_code!("protocols/ext-workspace-unstable-v1.xml");
+}
+
+/// Version of the protocol, the draft is still all some compositors offer
+#[derive(Clone, Copy)]
+pub enum Protocol {
+    Ext,
+    Zext,
+}
+
+enum Manager {
+    Ext(ExtWorkspaceManagerV1),
+    Zext(ZextWorkspaceManagerV1),
+}
+
+impl Manager {
+    fn commit(&self) {
+        match self {
+            Self::Ext(manager) => manager.commit(),
+            Self::Zext(manager) => manager.commit(),
+        }
+    }
+}
+
+#[derive(Clone)]
+enum Handle {
+    Ext(ExtWorkspaceHandleV1),
+    Zext(ZextWorkspaceHandleV1),
+}
+
+impl Handle {
+    fn id(&self) -> ObjectId {
+        match self {
+            Self::Ext(handle) => handle.id(),
+            Self::Zext(handle) => handle.id(),
+        }
+    }
+
+    fn activate(&self) {
+        match self {
+            Self::Ext(handle) => handle.activate(),
+            Self::Zext(handle) => handle.activate(),
+        }
+    }
+}
+
+struct Entry {
+    handle: Handle,
+    group: Option<ObjectId>,
+    name: Box<str>,
+    coordinates: Vec<u32>,
+    // Draft states are converted to the flags of the stable protocol
+    state: ext_workspace_handle_v1::State,
+}
+
+impl Entry {
+    fn new(handle: Handle, group: Option<ObjectId>) -> Self {
+        Self {
+            handle,
+            group,
+            name: "".into(),
+            coordinates: Vec::new(),
+            state: ext_workspace_handle_v1::State::empty(),
+        }
+    }
+}
+
+struct Group {
+    id: ObjectId,
+    outputs: Vec<wl_output::WlOutput>,
+}
+
+/// Arrays of both protocols hold native endian u32 values
+fn words(array: &[u8]) -> impl Iterator<Item = u32> + '_ {
+    array
+        .chunks_exact(4)
+        .map(|bytes| u32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+/// Receives protocol events on its own connection, changes are published on `done`
+struct Dispatcher {
+    entries: Vec<Entry>,
+    groups: Vec<Group>,
+    outputs: Vec<(wl_output::WlOutput, Box<str>)>,
+    workspaces: Arc<Mutex<Vec<(Workspace, Handle)>>>,
+    tx: broadcast::Sender<()>,
+}
+
+impl Dispatcher {
+    fn publish(&mut self) {
+        // Workspaces without a group go last
+        let group_index = |entry: &Entry| {
+            entry
+                .group
+                .as_ref()
+                .and_then(|id| self.groups.iter().position(|group| group.id == *id))
+                .unwrap_or(self.groups.len())
+        };
+
+        let mut entries = self
+            .entries
+            .iter()
+            .filter(|entry| !entry.state.contains(ext_workspace_handle_v1::State::Hidden))
+            .collect::<Vec<_>>();
+        // Stable sort keeps creation order for workspaces without coordinates
+        entries.sort_by(|a, b| {
+            (group_index(a), &a.coordinates).cmp(&(group_index(b), &b.coordinates))
+        });
+
+        // Numbered per group like other backends number them per output. Neither protocol tells
+        // which output is focused, so no workspace is marked focused and bars go by visible ones
+        let mut workspaces = Vec::with_capacity(entries.len());
+        let mut previous = None;
+        let mut id = 0;
+        for entry in entries {
+            let group = group_index(entry);
+            id = if previous == Some(group) { id + 1 } else { 1 };
+            previous = Some(group);
+
+            let workspace = Workspace {
+                id,
+                name: entry.name.clone(),
+                output: self.output_name(entry),
+                focused: false,
+                visible: entry.state.contains(ext_workspace_handle_v1::State::Active),
+                urgent: entry.state.contains(ext_workspace_handle_v1::State::Urgent),
+                // Windows aren't part of the protocol
+                windows: 0,
+            };
+            workspaces.push((workspace, entry.handle.clone()));
+        }
+
+        *self.workspaces.lock().unwrap() = workspaces;
+        _ = self.tx.send(());
+    }
+
+    /// Name of the first output of the workspace group, empty when unknown
+    fn output_name(&self, entry: &Entry) -> Box<str> {
+        let output = self
+            .groups
+            .iter()
+            .find(|group| entry.group.as_ref() == Some(&group.id))
+            .and_then(|group| group.outputs.first());
+
+        self.outputs
+            .iter()
+            .find(|(wl_output, _)| Some(wl_output) == output)
+            .map(|(_, name)| name.clone())
+            .unwrap_or_default()
+    }
+
+    fn group_event(&mut self, id: ObjectId, event: GroupEvent) {
+        let Some(group) = self.groups.iter_mut().find(|group| group.id == id) else {
+            return;
+        };
+
+        match event {
+            GroupEvent::OutputEnter(output) => group.outputs.push(output),
+            GroupEvent::OutputLeave(output) => {
+                group.outputs.retain(|wl_output| *wl_output != output)
+            }
+            GroupEvent::Removed => self.groups.retain(|group| group.id != id),
+        }
+    }
+
+    fn entry(&mut self, id: ObjectId) -> Option<&mut Entry> {
+        self.entries
+            .iter_mut()
+            .find(|entry| entry.handle.id() == id)
+    }
+}
+
+/// Group events both protocols have in common
+enum GroupEvent {
+    OutputEnter(wl_output::WlOutput),
+    OutputLeave(wl_output::WlOutput),
+    Removed,
+}
+
+/// Workspaces through the `ext-workspace-v1` protocol or its `zext_workspace_v1` draft, works on
+/// any compositor implementing either
+pub struct ExtWorkspace {
+    conn: Connection,
+    manager: Manager,
+    workspaces: Arc<Mutex<Vec<(Workspace, Handle)>>>,
+    tx: broadcast::Sender<()>,
+}
+
+impl ExtWorkspace {
+    pub fn new(protocol: Protocol) -> anyhow::Result<Self> {
+        Self::connect(Connection::connect_to_env()?, protocol)
+    }
+
+    fn connect(conn: Connection, protocol: Protocol) -> anyhow::Result<Self> {
+        let (globals, mut queue) = registry_queue_init::<Dispatcher>(&conn)?;
+        let qh = queue.handle();
+        let manager = match protocol {
+            Protocol::Ext => Manager::Ext(globals.bind(&qh, 1..=1, ())?),
+            Protocol::Zext => Manager::Zext(globals.bind(&qh, 1..=1, ())?),
+        };
+        // Output names are needed to match workspace groups with bars
+        globals.contents().with_list(|list| {
+            list.iter()
+                .filter(|global| global.interface == wl_output::WlOutput::interface().name)
+                .for_each(|global| {
+                    globals.registry().bind::<wl_output::WlOutput, _, _>(
+                        global.name,
+                        global.version.min(4),
+                        &qh,
+                        (),
+                    );
+                });
+        });
+
+        let workspaces = Arc::default();
+        let tx = broadcast::Sender::new(1);
+        let mut dispatcher = Dispatcher {
+            entries: Vec::new(),
+            groups: Vec::new(),
+            outputs: Vec::new(),
+            workspaces: Arc::clone(&workspaces),
+            tx: tx.clone(),
+        };
+        // Initial state is sent right after binding, output names may arrive after its `done`
+        queue.roundtrip(&mut dispatcher)?;
+        dispatcher.publish();
+
+        thread::spawn(move || loop {
+            if let Err(e) = queue.blocking_dispatch(&mut dispatcher) {
+                warn!("Lost connection to workspace manager\n {}", e);
+                return;
+            }
+        });
+
+        Ok(Self {
+            conn,
+            manager,
+            workspaces,
+            tx,
+        })
+    }
+}
+
+impl ExtWorkspace {
+    fn activate(&self, predicate: impl Fn(&Workspace) -> bool) -> anyhow::Result<()> {
+        let workspaces = self.workspaces.lock().unwrap();
+        let (_, handle) = workspaces
+            .iter()
+            .find(|(workspace, _)| predicate(workspace))
+            .ok_or_else(|| anyhow::anyhow!("Workspace not found"))?;
+
+        handle.activate();
+        self.manager.commit();
+        Ok(self.conn.flush()?)
+    }
+}
+
+impl Compositor for ExtWorkspace {
+    fn workspaces(&self) -> anyhow::Result<Vec<Workspace>> {
+        Ok(self
+            .workspaces
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(workspace, _)| workspace.clone())
+            .collect())
+    }
+
+    fn switch_workspace(&self, id: i32) -> anyhow::Result<()> {
+        self.activate(|workspace| workspace.id == id)
+    }
+
+    // Workspaces are numbered per group, so every output has its own
+    fn switch_workspace_on(&self, id: i32, output: &str) -> anyhow::Result<()> {
+        self.activate(|workspace| workspace.id == id && *workspace.output == *output)
+    }
+
+    fn switch_workspace_name(&self, name: &str) -> anyhow::Result<()> {
+        self.activate(|workspace| *workspace.name == *name)
+    }
+
+    fn listen(&self, tx: &broadcast::Sender<()>) -> anyhow::Result<()> {
+        let mut rx = self.tx.subscribe();
+        loop {
+            match rx.blocking_recv() {
+                Err(RecvError::Closed) => return Ok(()),
+                _ => {
+                    _ = tx.send(());
+                }
+            }
+        }
+    }
+}
+
+impl Dispatch<wl_registry::WlRegistry, GlobalListContents> for Dispatcher {
+    fn event(
+        _state: &mut Self,
+        proxy: &wl_registry::WlRegistry,
+        event: wl_registry::Event,
+        _data: &GlobalListContents,
+        _conn: &Connection,
+        qh: &QueueHandle<Self>,
+    ) {
+        if let wl_registry::Event::Global {
+            name,
+            interface,
+            version,
+        } = event
+        {
+            if interface == wl_output::WlOutput::interface().name {
+                proxy.bind::<wl_output::WlOutput, _, _>(name, version.min(4), qh, ());
+            }
+        }
+    }
+}
+
+impl Dispatch<wl_output::WlOutput, ()> for Dispatcher {
+    fn event(
+        state: &mut Self,
+        proxy: &wl_output::WlOutput,
+        event: wl_output::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        if let wl_output::Event::Name { name } = event {
+            state.outputs.retain(|(output, _)| output != proxy);
+            state.outputs.push((proxy.clone(), name.into()));
+        }
+    }
+}
+
+impl Dispatch<ExtWorkspaceManagerV1, ()> for Dispatcher {
+    fn event(
+        state: &mut Self,
+        _proxy: &ExtWorkspaceManagerV1,
+        event: ext_workspace_manager_v1::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        match event {
+            ext_workspace_manager_v1::Event::WorkspaceGroup { workspace_group } => {
+                state.groups.push(Group {
+                    id: workspace_group.id(),
+                    outputs: Vec::new(),
+                })
+            }
+            ext_workspace_manager_v1::Event::Workspace { workspace } => {
+                state.entries.push(Entry::new(Handle::Ext(workspace), None))
+            }
+            ext_workspace_manager_v1::Event::Done => state.publish(),
+            _ => {}
+        }
+    }
+
+    event_created_child!(Dispatcher, ExtWorkspaceManagerV1, [
+        ext_workspace_manager_v1::EVT_WORKSPACE_GROUP_OPCODE => (ExtWorkspaceGroupHandleV1, ()),
+        ext_workspace_manager_v1::EVT_WORKSPACE_OPCODE => (ExtWorkspaceHandleV1, ()),
+    ]);
+}
+
+impl Dispatch<ExtWorkspaceGroupHandleV1, ()> for Dispatcher {
+    fn event(
+        state: &mut Self,
+        proxy: &ExtWorkspaceGroupHandleV1,
+        event: ext_workspace_group_handle_v1::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        match event {
+            ext_workspace_group_handle_v1::Event::OutputEnter { output } => {
+                state.group_event(proxy.id(), GroupEvent::OutputEnter(output))
+            }
+            ext_workspace_group_handle_v1::Event::OutputLeave { output } => {
+                state.group_event(proxy.id(), GroupEvent::OutputLeave(output))
+            }
+            ext_workspace_group_handle_v1::Event::WorkspaceEnter { workspace } => {
+                if let Some(entry) = state.entry(workspace.id()) {
+                    entry.group = Some(proxy.id());
+                }
+            }
+            ext_workspace_group_handle_v1::Event::WorkspaceLeave { workspace } => {
+                if let Some(entry) = state.entry(workspace.id()) {
+                    entry.group = None;
+                }
+            }
+            ext_workspace_group_handle_v1::Event::Removed => {
+                state.group_event(proxy.id(), GroupEvent::Removed);
+                proxy.destroy();
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<ExtWorkspaceHandleV1, ()> for Dispatcher {
+    fn event(
+        state: &mut Self,
+        proxy: &ExtWorkspaceHandleV1,
+        event: ext_workspace_handle_v1::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        if let ext_workspace_handle_v1::Event::Removed = event {
+            state
+                .entries
+                .retain(|entry| entry.handle.id() != proxy.id());
+            proxy.destroy();
+            return;
+        }
+
+        let Some(entry) = state.entry(proxy.id()) else {
+            return;
+        };
+
+        match event {
+            ext_workspace_handle_v1::Event::Coordinates { coordinates } => {
+                entry.coordinates = words(&coordinates).collect();
+            }
+            ext_workspace_handle_v1::Event::Name { name } => entry.name = name.into(),
+            ext_workspace_handle_v1::Event::State {
+                state: WEnum::Value(workspace_state),
+            } => entry.state = workspace_state,
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<ZextWorkspaceManagerV1, ()> for Dispatcher {
+    fn event(
+        state: &mut Self,
+        _proxy: &ZextWorkspaceManagerV1,
+        event: zext_workspace_manager_v1::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        match event {
+            zext_workspace_manager_v1::Event::WorkspaceGroup { workspace_group } => {
+                state.groups.push(Group {
+                    id: workspace_group.id(),
+                    outputs: Vec::new(),
+                })
+            }
+            zext_workspace_manager_v1::Event::Done => state.publish(),
+            _ => {}
+        }
+    }
+
+    event_created_child!(Dispatcher, ZextWorkspaceManagerV1, [
+        zext_workspace_manager_v1::EVT_WORKSPACE_GROUP_OPCODE => (ZextWorkspaceGroupHandleV1, ()),
+    ]);
+}
+
+impl Dispatch<ZextWorkspaceGroupHandleV1, ()> for Dispatcher {
+    fn event(
+        state: &mut Self,
+        proxy: &ZextWorkspaceGroupHandleV1,
+        event: zext_workspace_group_handle_v1::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        match event {
+            zext_workspace_group_handle_v1::Event::OutputEnter { output } => {
+                state.group_event(proxy.id(), GroupEvent::OutputEnter(output))
+            }
+            zext_workspace_group_handle_v1::Event::OutputLeave { output } => {
+                state.group_event(proxy.id(), GroupEvent::OutputLeave(output))
+            }
+            // Draft workspaces are created by their group and never move to another one
+            zext_workspace_group_handle_v1::Event::Workspace { workspace } => state
+                .entries
+                .push(Entry::new(Handle::Zext(workspace), Some(proxy.id()))),
+            zext_workspace_group_handle_v1::Event::Remove => {
+                state.group_event(proxy.id(), GroupEvent::Removed);
+                proxy.destroy();
+            }
+            _ => {}
+        }
+    }
+
+    event_created_child!(Dispatcher, ZextWorkspaceGroupHandleV1, [
+        zext_workspace_group_handle_v1::EVT_WORKSPACE_OPCODE => (ZextWorkspaceHandleV1, ()),
+    ]);
+}
+
+impl Dispatch<ZextWorkspaceHandleV1, ()> for Dispatcher {
+    fn event(
+        state: &mut Self,
+        proxy: &ZextWorkspaceHandleV1,
+        event: zext_workspace_handle_v1::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        if let zext_workspace_handle_v1::Event::Remove = event {
+            state
+                .entries
+                .retain(|entry| entry.handle.id() != proxy.id());
+            proxy.destroy();
+            return;
+        }
+
+        let Some(entry) = state.entry(proxy.id()) else {
+            return;
+        };
+
+        match event {
+            zext_workspace_handle_v1::Event::Coordinates { coordinates } => {
+                entry.coordinates = words(&coordinates).collect();
+            }
+            zext_workspace_handle_v1::Event::Name { name } => entry.name = name.into(),
+            // A list of states rather than flags
+            zext_workspace_handle_v1::Event::State { state } => {
+                entry.state = words(&state)
+                    .filter_map(
+                        |value| match zext_workspace_handle_v1::State::try_from(value) {
+                            Ok(zext_workspace_handle_v1::State::Active) => {
+                                Some(ext_workspace_handle_v1::State::Active)
+                            }
+                            Ok(zext_workspace_handle_v1::State::Urgent) => {
+                                Some(ext_workspace_handle_v1::State::Urgent)
+                            }
+                            Ok(zext_workspace_handle_v1::State::Hidden) => {
+                                Some(ext_workspace_handle_v1::State::Hidden)
+                            }
+                            _ => None,
+                        },
+                    )
+                    .fold(ext_workspace_handle_v1::State::empty(), |state, flag| {
+                        state | flag
+                    });
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use server::{
+        ext_workspace_group_handle_v1::ExtWorkspaceGroupHandleV1,
+        ext_workspace_handle_v1::{self, ExtWorkspaceHandleV1},
+        ext_workspace_manager_v1::{self, ExtWorkspaceManagerV1},
+        zext_workspace_group_handle_v1::ZextWorkspaceGroupHandleV1,
+        zext_workspace_handle_v1::{self, ZextWorkspaceHandleV1},
+        zext_workspace_manager_v1::{self, ZextWorkspaceManagerV1},
+    };
+    use std::{os::unix::net::UnixStream, time::Duration};
+    use wayland_server::{
+        protocol::wl_output::{self, WlOutput},
+        Client, DataInit, Dispatch, Display, DisplayHandle, GlobalDispatch, New, Resource,
+    };
+
+    #[allow(
+        non_upper_case_globals,
+        non_camel_case_types,
+        unused_imports,
+        clippy::all
+    )]
+    mod server {
+        use wayland_server;
+        use wayland_server::protocol::*;
+
+        pub mod __interfaces {
+            use wayland_server::protocol::__interfaces::*;
+            wayland_scanner::generate_interfaces!("protocols/ext-workspace-v1.xml");
+            wayland_scanner::generate_interfaces!("protocols/ext-workspace-unstable-v1.xml");
+        }
+        use self::__interfaces::*;
+
+        wayland_scanner::generate_server_code!("protocols/ext-workspace-v1.xml");
+        wayland_scanner::generate_server_code!("protocols/ext-workspace-unstable-v1.xml");
+    }
+
+    #[derive(Clone, Copy, PartialEq)]
+    enum TestState {
+        Inactive,
+        Active,
+        Urgent,
+        Hidden,
+    }
+
+    // Workspaces of each output, sent out of order, hidden workspaces aren't shown
+    const LAYOUT: [(&str, &[(&str, u32, TestState)]); 2] = [
+        (
+            "DP-1",
+            &[
+                ("1", 1, TestState::Active),
+                ("3", 3, TestState::Urgent),
+                ("2", 2, TestState::Inactive),
+                ("scratch", 4, TestState::Hidden),
+            ],
+        ),
+        (
+            "HDMI-A-1",
+            &[
+                ("mail", 2, TestState::Inactive),
+                ("web", 1, TestState::Active),
+            ],
+        ),
+    ];
+
+    enum TestManager {
+        Ext(ExtWorkspaceManagerV1),
+        Zext(ZextWorkspaceManagerV1),
+    }
+
+    enum TestGroup {
+        Ext(ExtWorkspaceGroupHandleV1),
+        Zext(ZextWorkspaceGroupHandleV1),
+    }
+
+    enum TestWorkspace {
+        Ext(ExtWorkspaceHandleV1),
+        Zext(ZextWorkspaceHandleV1),
+    }
+
+    impl TestWorkspace {
+        fn state(&self, state: TestState) {
+            match self {
+                Self::Ext(workspace) => workspace.state(match state {
+                    TestState::Inactive => ext_workspace_handle_v1::State::empty(),
+                    TestState::Active => ext_workspace_handle_v1::State::Active,
+                    TestState::Urgent => ext_workspace_handle_v1::State::Urgent,
+                    TestState::Hidden => ext_workspace_handle_v1::State::Hidden,
+                }),
+                Self::Zext(workspace) => {
+                    let states: &[zext_workspace_handle_v1::State] = match state {
+                        TestState::Inactive => &[],
+                        TestState::Active => &[zext_workspace_handle_v1::State::Active],
+                        TestState::Urgent => &[zext_workspace_handle_v1::State::Urgent],
+                        TestState::Hidden => &[zext_workspace_handle_v1::State::Hidden],
+                    };
+                    workspace.state(
+                        states
+                            .iter()
+                            .flat_map(|state| (*state as u32).to_ne_bytes())
+                            .collect(),
+                    );
+                }
+            }
+        }
+
+        fn id(&self) -> wayland_server::backend::ObjectId {
+            match self {
+                Self::Ext(workspace) => workspace.id(),
+                Self::Zext(workspace) => workspace.id(),
+            }
+        }
+    }
+
+    /// Minimal compositor announcing a workspace group per output, in either protocol version
+    struct TestCompositor {
+        manager: Option<TestManager>,
+        // Output name of every group
+        groups: Vec<(&'static str, TestGroup)>,
+        // Index of the group every workspace belongs to
+        workspaces: Vec<(TestWorkspace, usize)>,
+        pending: Option<wayland_server::backend::ObjectId>,
+    }
+
+    impl TestCompositor {
+        fn done(&self) {
+            match &self.manager {
+                Some(TestManager::Ext(manager)) => manager.done(),
+                Some(TestManager::Zext(manager)) => manager.done(),
+                None => {}
+            }
+        }
+
+        /// Activates the pending workspace, deactivating the others of its group
+        fn commit(&mut self) {
+            let Some(pending) = self.pending.take() else {
+                return;
+            };
+            let Some(&(_, group)) = self
+                .workspaces
+                .iter()
+                .find(|(workspace, _)| workspace.id() == pending)
+            else {
+                return;
+            };
+
+            for (workspace, _) in self.workspaces.iter().filter(|(_, index)| *index == group) {
+                workspace.state(match workspace.id() == pending {
+                    true => TestState::Active,
+                    false => TestState::Inactive,
+                });
+            }
+            self.done();
+        }
+    }
+
+    fn serve(stream: UnixStream, protocol: Protocol) {
+        let mut display = Display::<TestCompositor>::new().unwrap();
+        let mut handle = display.handle();
+        match protocol {
+            Protocol::Ext => {
+                handle.create_global::<TestCompositor, ExtWorkspaceManagerV1, ()>(1, ());
+            }
+            Protocol::Zext => {
+                handle.create_global::<TestCompositor, ZextWorkspaceManagerV1, ()>(1, ());
+            }
+        }
+        for (output, _) in LAYOUT {
+            handle.create_global::<TestCompositor, WlOutput, &'static str>(4, output);
+        }
+        handle.insert_client(stream, Arc::new(())).unwrap();
+
+        let mut state = TestCompositor {
+            manager: None,
+            groups: Vec::new(),
+            workspaces: Vec::new(),
+            pending: None,
+        };
+        while display.dispatch_clients(&mut state).is_ok() && display.flush_clients().is_ok() {
+            thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    /// Announces every group and workspace of `LAYOUT`
+    fn announce(state: &mut TestCompositor, handle: &DisplayHandle, client: &Client) {
+        for (index, (output, workspaces)) in LAYOUT.into_iter().enumerate() {
+            let group = match &state.manager {
+                Some(TestManager::Ext(manager)) => {
+                    let group = client
+                        .create_resource::<ExtWorkspaceGroupHandleV1, (), TestCompositor>(
+                            handle,
+                            1,
+                            (),
+                        )
+                        .unwrap();
+                    manager.workspace_group(&group);
+                    TestGroup::Ext(group)
+                }
+                Some(TestManager::Zext(manager)) => {
+                    let group = client
+                        .create_resource::<ZextWorkspaceGroupHandleV1, (), TestCompositor>(
+                            handle,
+                            1,
+                            (),
+                        )
+                        .unwrap();
+                    manager.workspace_group(&group);
+                    TestGroup::Zext(group)
+                }
+                None => return,
+            };
+
+            for &(name, coordinate, workspace_state) in workspaces {
+                let workspace = match (&state.manager, &group) {
+                    (Some(TestManager::Ext(manager)), TestGroup::Ext(group)) => {
+                        let workspace = client
+                            .create_resource::<ExtWorkspaceHandleV1, (), TestCompositor>(
+                                handle,
+                                1,
+                                (),
+                            )
+                            .unwrap();
+                        manager.workspace(&workspace);
+                        workspace.name(name.into());
+                        workspace.coordinates(coordinate.to_ne_bytes().to_vec());
+                        group.workspace_enter(&workspace);
+                        TestWorkspace::Ext(workspace)
+                    }
+                    (_, TestGroup::Zext(group)) => {
+                        let workspace = client
+                            .create_resource::<ZextWorkspaceHandleV1, (), TestCompositor>(
+                                handle,
+                                1,
+                                (),
+                            )
+                            .unwrap();
+                        group.workspace(&workspace);
+                        workspace.name(name.into());
+                        workspace.coordinates(coordinate.to_ne_bytes().to_vec());
+                        TestWorkspace::Zext(workspace)
+                    }
+                    _ => return,
+                };
+                workspace.state(workspace_state);
+                state.workspaces.push((workspace, index));
+            }
+            state.groups.push((output, group));
+        }
+        state.done();
+    }
+
+    impl GlobalDispatch<ExtWorkspaceManagerV1, ()> for TestCompositor {
+        fn bind(
+            state: &mut Self,
+            handle: &DisplayHandle,
+            client: &Client,
+            resource: New<ExtWorkspaceManagerV1>,
+            _global_data: &(),
+            data_init: &mut DataInit<'_, Self>,
+        ) {
+            state.manager = Some(TestManager::Ext(data_init.init(resource, ())));
+            announce(state, handle, client);
+        }
+    }
+
+    impl GlobalDispatch<ZextWorkspaceManagerV1, ()> for TestCompositor {
+        fn bind(
+            state: &mut Self,
+            handle: &DisplayHandle,
+            client: &Client,
+            resource: New<ZextWorkspaceManagerV1>,
+            _global_data: &(),
+            data_init: &mut DataInit<'_, Self>,
+        ) {
+            state.manager = Some(TestManager::Zext(data_init.init(resource, ())));
+            announce(state, handle, client);
+        }
+    }
+
+    impl GlobalDispatch<WlOutput, &'static str> for TestCompositor {
+        fn bind(
+            state: &mut Self,
+            _handle: &DisplayHandle,
+            _client: &Client,
+            resource: New<WlOutput>,
+            name: &&'static str,
+            data_init: &mut DataInit<'_, Self>,
+        ) {
+            let output = data_init.init(resource, ());
+            output.name(name.to_string());
+            for (_, group) in state.groups.iter().filter(|(output, _)| output == name) {
+                match group {
+                    TestGroup::Ext(group) => group.output_enter(&output),
+                    TestGroup::Zext(group) => group.output_enter(&output),
+                }
+            }
+            state.done();
+        }
+    }
+
+    impl Dispatch<ExtWorkspaceManagerV1, ()> for TestCompositor {
+        fn request(
+            state: &mut Self,
+            _client: &Client,
+            _resource: &ExtWorkspaceManagerV1,
+            request: ext_workspace_manager_v1::Request,
+            _data: &(),
+            _handle: &DisplayHandle,
+            _data_init: &mut DataInit<'_, Self>,
+        ) {
+            if let ext_workspace_manager_v1::Request::Commit = request {
+                state.commit();
+            }
+        }
+    }
+
+    impl Dispatch<ZextWorkspaceManagerV1, ()> for TestCompositor {
+        fn request(
+            state: &mut Self,
+            _client: &Client,
+            _resource: &ZextWorkspaceManagerV1,
+            request: zext_workspace_manager_v1::Request,
+            _data: &(),
+            _handle: &DisplayHandle,
+            _data_init: &mut DataInit<'_, Self>,
+        ) {
+            if let zext_workspace_manager_v1::Request::Commit = request {
+                state.commit();
+            }
+        }
+    }
+
+    impl Dispatch<ExtWorkspaceHandleV1, ()> for TestCompositor {
+        fn request(
+            state: &mut Self,
+            _client: &Client,
+            resource: &ExtWorkspaceHandleV1,
+            request: ext_workspace_handle_v1::Request,
+            _data: &(),
+            _handle: &DisplayHandle,
+            _data_init: &mut DataInit<'_, Self>,
+        ) {
+            if let ext_workspace_handle_v1::Request::Activate = request {
+                state.pending = Some(resource.id());
+            }
+        }
+    }
+
+    impl Dispatch<ZextWorkspaceHandleV1, ()> for TestCompositor {
+        fn request(
+            state: &mut Self,
+            _client: &Client,
+            resource: &ZextWorkspaceHandleV1,
+            request: zext_workspace_handle_v1::Request,
+            _data: &(),
+            _handle: &DisplayHandle,
+            _data_init: &mut DataInit<'_, Self>,
+        ) {
+            if let zext_workspace_handle_v1::Request::Activate = request {
+                state.pending = Some(resource.id());
+            }
+        }
+    }
+
+    impl Dispatch<ExtWorkspaceGroupHandleV1, ()> for TestCompositor {
+        fn request(
+            _state: &mut Self,
+            _client: &Client,
+            _resource: &ExtWorkspaceGroupHandleV1,
+            _request: server::ext_workspace_group_handle_v1::Request,
+            _data: &(),
+            _handle: &DisplayHandle,
+            _data_init: &mut DataInit<'_, Self>,
+        ) {
+        }
+    }
+
+    impl Dispatch<ZextWorkspaceGroupHandleV1, ()> for TestCompositor {
+        fn request(
+            _state: &mut Self,
+            _client: &Client,
+            _resource: &ZextWorkspaceGroupHandleV1,
+            _request: server::zext_workspace_group_handle_v1::Request,
+            _data: &(),
+            _handle: &DisplayHandle,
+            _data_init: &mut DataInit<'_, Self>,
+        ) {
+        }
+    }
+
+    impl Dispatch<WlOutput, ()> for TestCompositor {
+        fn request(
+            _state: &mut Self,
+            _client: &Client,
+            _resource: &WlOutput,
+            _request: wl_output::Request,
+            _data: &(),
+            _handle: &DisplayHandle,
+            _data_init: &mut DataInit<'_, Self>,
+        ) {
+        }
+    }
+
+    fn test_protocol(protocol: Protocol) {
+        let (client, server) = UnixStream::pair().unwrap();
+        thread::spawn(move || serve(server, protocol));
+        let backend =
+            ExtWorkspace::connect(Connection::from_socket(client).unwrap(), protocol).unwrap();
+
+        // Numbered per output, sorted by coordinates
+        let workspaces = backend.workspaces().unwrap();
+        let summary = workspaces
+            .iter()
+            .map(|workspace| {
+                (
+                    workspace.id,
+                    &*workspace.name,
+                    &*workspace.output,
+                    workspace.visible,
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            [
+                (1, "1", "DP-1", true),
+                (2, "2", "DP-1", false),
+                (3, "3", "DP-1", false),
+                (1, "web", "HDMI-A-1", true),
+                (2, "mail", "HDMI-A-1", false),
+            ]
+        );
+        assert!(workspaces[2].urgent);
+        // Neither protocol knows which output is focused
+        assert!(workspaces.iter().all(|workspace| !workspace.focused));
+
+        let mut rx = backend.tx.subscribe();
+        backend.switch_workspace_on(2, "HDMI-A-1").unwrap();
+        rx.blocking_recv().unwrap();
+        let visible = |workspaces: Vec<Workspace>| {
+            workspaces
+                .into_iter()
+                .filter(|workspace| workspace.visible)
+                .map(|workspace| workspace.name)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            visible(backend.workspaces().unwrap()),
+            [Box::from("1"), Box::from("mail")]
+        );
+
+        backend.switch_workspace_name("2").unwrap();
+        rx.blocking_recv().unwrap();
+        assert_eq!(
+            visible(backend.workspaces().unwrap()),
+            [Box::from("2"), Box::from("mail")]
+        );
+    }
+
+    #[test]
+    fn test_compositor() {
+        test_protocol(Protocol::Ext);
+    }
+
+    #[test]
+    fn draft_protocol() {
+        test_protocol(Protocol::Zext);
+    }
+}
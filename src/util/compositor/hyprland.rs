@@ -0,0 +1,214 @@
+use super::{Compositor, Window, Workspace};
+use hyprland::{
+    ctl::switch_xkb_layout::{self, SwitchXKBLayoutCmdTypes},
+    dispatch::{Dispatch, DispatchType, WorkspaceIdentifierWithSpecial},
+    event_listener::EventListener,
+    shared::{Address, HyprData, HyprDataActive, HyprDataActiveOptional},
+};
+use lazy_static::lazy_static;
+use std::sync::Mutex;
+use tokio::sync::broadcast;
+
+lazy_static! {
+    // Hyprland only reports urgency as events, windows stay urgent until focused or closed
+    static ref URGENT: Mutex<Vec<Address>> = Mutex::new(Vec::new());
+    // Submaps can't be queried either, so it's only known after the first change
+    static ref SUBMAP: Mutex<Box<str>> = Mutex::new("".into());
+}
+
+pub struct Hyprland;
+
+impl Compositor for Hyprland {
+    fn workspaces(&self) -> anyhow::Result<Vec<Workspace>> {
+        let active = hyprland::data::Workspace::get_active()?.id;
+        let visible = hyprland::data::Monitors::get()?
+            .into_iter()
+            .map(|monitor| monitor.active_workspace.id)
+            .collect::<Vec<_>>();
+        let urgent = URGENT.lock().unwrap().clone();
+        let urgent = match urgent.is_empty() {
+            true => Vec::new(),
+            false => hyprland::data::Clients::get()?
+                .into_iter()
+                .filter(|client| urgent.contains(&client.address))
+                .map(|client| client.workspace.id)
+                .collect(),
+        };
+
+        Ok(hyprland::data::Workspaces::get()?
+            .into_iter()
+            .map(|workspace| Workspace {
+                id: workspace.id,
+                name: workspace.name.into(),
+                output: workspace.monitor.into(),
+                focused: workspace.id == active,
+                visible: visible.contains(&workspace.id),
+                urgent: urgent.contains(&workspace.id),
+                windows: workspace.windows as usize,
+            })
+            .collect())
+    }
+
+    fn switch_workspace(&self, id: i32) -> anyhow::Result<()> {
+        Ok(Dispatch::call(DispatchType::Workspace(
+            WorkspaceIdentifierWithSpecial::Id(id),
+        ))?)
+    }
+
+    fn switch_workspace_name(&self, name: &str) -> anyhow::Result<()> {
+        let dispatch = match name.strip_prefix("special:") {
+            Some(special) => DispatchType::ToggleSpecialWorkspace(Some(special.to_string())),
+            None => DispatchType::Workspace(WorkspaceIdentifierWithSpecial::Name(name)),
+        };
+        Ok(Dispatch::call(dispatch)?)
+    }
+
+    fn outputs(&self) -> anyhow::Result<Vec<Box<str>>> {
+        let mut monitors = hyprland::data::Monitors::get()?
+            .into_iter()
+            .collect::<Vec<_>>();
+        monitors.sort_by_key(|monitor| (monitor.x, monitor.y));
+        Ok(monitors
+            .into_iter()
+            .map(|monitor| monitor.name.into())
+            .collect())
+    }
+
+    fn focused_window(&self) -> anyhow::Result<Option<Window>> {
+        Ok(hyprland::data::Client::get_active()?.map(|client| Window {
+            title: client.title.into(),
+            app_id: client.class.into(),
+        }))
+    }
+
+    fn keyboard_layout(&self) -> anyhow::Result<Box<str>> {
+        // Layout switches apply to all keyboards, the main one is what Hyprland itself reports
+        hyprland::data::Devices::get()?
+            .keyboards
+            .into_iter()
+            .find(|keyboard| keyboard.main)
+            .map(|keyboard| keyboard.active_keymap.into())
+            .ok_or_else(|| anyhow::anyhow!("No keyboard found"))
+    }
+
+    fn switch_keyboard_layout(&self, next: bool) -> anyhow::Result<()> {
+        let cmd = match next {
+            true => SwitchXKBLayoutCmdTypes::Next,
+            false => SwitchXKBLayoutCmdTypes::Previous,
+        };
+        Ok(switch_xkb_layout::call("all", cmd)?)
+    }
+
+    fn scratchpad(&self) -> anyhow::Result<usize> {
+        Ok(hyprland::data::Workspaces::get()?
+            .into_iter()
+            .filter(|workspace| workspace.name.starts_with("special"))
+            .map(|workspace| workspace.windows as usize)
+            .sum())
+    }
+
+    fn show_scratchpad(&self) -> anyhow::Result<()> {
+        Ok(Dispatch::call(DispatchType::ToggleSpecialWorkspace(None))?)
+    }
+
+    fn binding_mode(&self) -> anyhow::Result<Box<str>> {
+        let submap = SUBMAP.lock().unwrap();
+        Ok(match submap.is_empty() {
+            true => "default".into(),
+            false => submap.clone(),
+        })
+    }
+
+    fn listen(&self, tx: &broadcast::Sender<()>) -> anyhow::Result<()> {
+        let mut listener = EventListener::new();
+
+        {
+            let tx = tx.clone();
+            listener.add_workspace_destroy_handler(move |_| {
+                _ = tx.send(());
+            });
+        }
+
+        {
+            let tx = tx.clone();
+            listener.add_workspace_change_handler(move |_| {
+                _ = tx.send(());
+            });
+        }
+
+        {
+            let tx = tx.clone();
+            listener.add_active_monitor_change_handler(move |_| {
+                _ = tx.send(());
+            });
+        }
+
+        {
+            let tx = tx.clone();
+            listener.add_active_window_change_handler(move |window| {
+                if let Some(window) = window {
+                    URGENT
+                        .lock()
+                        .unwrap()
+                        .retain(|address| *address != window.window_address);
+                }
+                _ = tx.send(());
+            });
+        }
+
+        {
+            let tx = tx.clone();
+            listener.add_urgent_state_handler(move |address| {
+                URGENT.lock().unwrap().push(address);
+                _ = tx.send(());
+            });
+        }
+
+        {
+            let tx = tx.clone();
+            listener.add_window_title_change_handler(move |_| {
+                _ = tx.send(());
+            });
+        }
+
+        // Window counts change with these
+        {
+            let tx = tx.clone();
+            listener.add_window_open_handler(move |_| {
+                _ = tx.send(());
+            });
+        }
+
+        {
+            let tx = tx.clone();
+            listener.add_window_close_handler(move |address| {
+                URGENT.lock().unwrap().retain(|urgent| *urgent != address);
+                _ = tx.send(());
+            });
+        }
+
+        {
+            let tx = tx.clone();
+            listener.add_window_moved_handler(move |_| {
+                _ = tx.send(());
+            });
+        }
+
+        {
+            let tx = tx.clone();
+            listener.add_keyboard_layout_change_handler(move |_| {
+                _ = tx.send(());
+            });
+        }
+
+        {
+            let tx = tx.clone();
+            listener.add_sub_map_change_handler(move |submap| {
+                *SUBMAP.lock().unwrap() = submap.into();
+                _ = tx.send(());
+            });
+        }
+
+        Ok(listener.start_listener()?)
+    }
+}
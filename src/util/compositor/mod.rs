@@ -0,0 +1,187 @@
+mod ext_workspace;
+mod hyprland;
+mod niri;
+mod river;
+mod sway;
+
+use lazy_static::lazy_static;
+use log::{info, warn};
+use tokio::sync::broadcast;
+
+lazy_static! {
+    static ref COMPOSITOR: Option<Box<dyn Compositor>> = detect();
+}
+
+#[derive(Clone)]
+pub struct Workspace {
+    pub id: i32,
+    pub name: Box<str>,
+    // Name of the output the workspace is on
+    pub output: Box<str>,
+    pub focused: bool,
+    // Shown on its output, focused workspace is visible too
+    pub visible: bool,
+    pub urgent: bool,
+    // Not every backend knows about windows, these report 0
+    pub windows: usize,
+}
+
+pub struct Window {
+    pub title: Box<str>,
+    // Wayland app_id, or class for X11 windows
+    pub app_id: Box<str>,
+}
+
+/// Compositor specific IPC, selected once at startup
+pub trait Compositor: Send + Sync {
+    fn workspaces(&self) -> anyhow::Result<Vec<Workspace>>;
+
+    fn switch_workspace(&self, id: i32) -> anyhow::Result<()>;
+
+    /// Switches to a workspace of given output, only needed where ids aren't unique across outputs
+    fn switch_workspace_on(&self, id: i32, _output: &str) -> anyhow::Result<()> {
+        self.switch_workspace(id)
+    }
+
+    fn switch_workspace_name(&self, name: &str) -> anyhow::Result<()>;
+
+    /// Names of active outputs from left to right, protocol based backends only know outputs
+    /// with workspaces and sort them by name
+    fn outputs(&self) -> anyhow::Result<Vec<Box<str>>> {
+        let mut outputs: Vec<_> = self
+            .workspaces()?
+            .into_iter()
+            .map(|workspace| workspace.output)
+            .collect();
+        outputs.sort();
+        outputs.dedup();
+        Ok(outputs)
+    }
+
+    // Protocol based backends only know about workspaces
+    fn focused_window(&self) -> anyhow::Result<Option<Window>> {
+        Err(anyhow::anyhow!("Not supported by the compositor"))
+    }
+
+    fn keyboard_layout(&self) -> anyhow::Result<Box<str>> {
+        Err(anyhow::anyhow!("Not supported by the compositor"))
+    }
+
+    fn switch_keyboard_layout(&self, _next: bool) -> anyhow::Result<()> {
+        Err(anyhow::anyhow!("Not supported by the compositor"))
+    }
+
+    /// Number of windows in the sway scratchpad or Hyprland special workspaces
+    fn scratchpad(&self) -> anyhow::Result<usize> {
+        Err(anyhow::anyhow!("Not supported by the compositor"))
+    }
+
+    fn show_scratchpad(&self) -> anyhow::Result<()> {
+        Err(anyhow::anyhow!("Not supported by the compositor"))
+    }
+
+    /// Sway binding mode or Hyprland submap, `default` when none is active
+    fn binding_mode(&self) -> anyhow::Result<Box<str>> {
+        Err(anyhow::anyhow!("Not supported by the compositor"))
+    }
+
+    /// Blocks sending to `tx` on workspace, output, window, keyboard layout and mode changes
+    fn listen(&self, tx: &broadcast::Sender<()>) -> anyhow::Result<()>;
+}
+
+fn detect() -> Option<Box<dyn Compositor>> {
+    if std::env::var("HYPRLAND_INSTANCE_SIGNATURE").is_ok() {
+        return Some(Box::new(hyprland::Hyprland));
+    }
+
+    if std::env::var("SWAYSOCK").is_ok() {
+        return Some(Box::new(sway::Sway));
+    }
+
+    if let Ok(socket) = std::env::var("NIRI_SOCKET") {
+        return Some(Box::new(niri::Niri::new(socket.into())));
+    }
+
+    // River has no socket environment variable, its protocol being available is checked instead
+    if let Ok(compositor) = river::River::new() {
+        info!("Using river status protocol for workspaces");
+        return Some(Box::new(compositor));
+    }
+
+    if let Ok(compositor) = ext_workspace::ExtWorkspace::new(ext_workspace::Protocol::Ext) {
+        info!("Using ext-workspace-v1 for workspaces");
+        return Some(Box::new(compositor));
+    }
+
+    match ext_workspace::ExtWorkspace::new(ext_workspace::Protocol::Zext) {
+        Ok(compositor) => {
+            info!("Using zext_workspace_v1 for workspaces");
+            Some(Box::new(compositor))
+        }
+        Err(e) => {
+            warn!("Unsupported compositor, disabling its modules\n {}", e);
+            None
+        }
+    }
+}
+
+/// Running compositor, `None` when it's not supported
+pub fn compositor() -> Option<&'static dyn Compositor> {
+    COMPOSITOR.as_deref()
+}
+
+/// Running compositor, errors when it's not supported
+pub fn get() -> anyhow::Result<&'static dyn Compositor> {
+    compositor().ok_or_else(|| anyhow::anyhow!("No supported compositor found"))
+}
+
+/// Workspaces on given output sorted by id, all of them grouped by output from left to right
+/// when it's `None`
+pub fn workspaces(output: Option<&str>) -> anyhow::Result<Vec<Workspace>> {
+    let compositor = get()?;
+    let mut workspaces = compositor.workspaces()?;
+    let outputs = match output {
+        Some(output) => {
+            workspaces.retain(|workspace| *workspace.output == *output);
+            Vec::new()
+        }
+        None => compositor.outputs()?,
+    };
+
+    // Outputs unknown to the compositor, e.g. disabled ones, go last
+    let position = |workspace: &Workspace| {
+        outputs
+            .iter()
+            .position(|output| *output == workspace.output)
+            .unwrap_or(outputs.len())
+    };
+    workspaces.sort_by(|a, b| (position(a), &a.output, a.id).cmp(&(position(b), &b.output, b.id)));
+
+    Ok(workspaces)
+}
+
+/// Switches to the next or previous workspace of given output, wraps around
+pub fn cycle_workspace(output: Option<&str>, forward: bool) -> anyhow::Result<()> {
+    let workspaces = workspaces(output)?;
+    let Some(current) = workspaces
+        .iter()
+        .position(|workspace| workspace.focused)
+        .or_else(|| workspaces.iter().position(|workspace| workspace.visible))
+    else {
+        return Ok(());
+    };
+
+    let next = match forward {
+        true => (current + 1) % workspaces.len(),
+        false => (current + workspaces.len() - 1) % workspaces.len(),
+    };
+    switch_to(&workspaces[next])
+}
+
+/// Switches to a listed workspace, named sway workspaces have no number and report -1
+pub fn switch_to(workspace: &Workspace) -> anyhow::Result<()> {
+    match workspace.id < 0 {
+        true => get()?.switch_workspace_name(&workspace.name),
+        false => get()?.switch_workspace_on(workspace.id, &workspace.output),
+    }
+}
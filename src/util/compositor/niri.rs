@@ -0,0 +1,285 @@
+use super::{Compositor, Window, Workspace};
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::{json, Value};
+use std::{
+    collections::HashMap,
+    io::{BufRead, BufReader, Write},
+    os::unix::net::UnixStream,
+    path::PathBuf,
+};
+use tokio::sync::broadcast;
+
+#[derive(Deserialize)]
+struct NiriWorkspace {
+    id: u64,
+    // Position on its output, starting at 1
+    idx: u8,
+    name: Option<String>,
+    output: Option<String>,
+    // Only reported by newer versions
+    #[serde(default)]
+    is_urgent: bool,
+    is_active: bool,
+    is_focused: bool,
+}
+
+#[derive(Deserialize)]
+struct NiriWindow {
+    title: Option<String>,
+    app_id: Option<String>,
+    workspace_id: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct NiriOutput {
+    // Missing for disabled outputs
+    logical: Option<LogicalOutput>,
+}
+
+#[derive(Deserialize)]
+struct LogicalOutput {
+    x: i32,
+    y: i32,
+}
+
+#[derive(Deserialize)]
+struct KeyboardLayouts {
+    names: Vec<String>,
+    current_idx: u8,
+}
+
+/// niri's JSON IPC, every request is sent on a new connection to `socket`
+pub struct Niri {
+    socket: PathBuf,
+}
+
+impl Niri {
+    pub fn new(socket: PathBuf) -> Self {
+        Self { socket }
+    }
+
+    fn request(&self, request: Value) -> anyhow::Result<Value> {
+        let mut stream = UnixStream::connect(&self.socket)?;
+        writeln!(stream, "{request}")?;
+
+        let mut reply = String::new();
+        BufReader::new(stream).read_line(&mut reply)?;
+        let reply: Result<Value, String> = serde_json::from_str(&reply)?;
+        reply.map_err(|e| anyhow::anyhow!("niri refused the request\n {}", e))
+    }
+
+    /// Responses are tagged with the name of the request, e.g. `{"Workspaces": [...]}`
+    fn query<T: DeserializeOwned>(&self, name: &str) -> anyhow::Result<T> {
+        let mut response = self.request(json!(name))?;
+        let response = response.get_mut(name).map(Value::take).unwrap_or_default();
+        Ok(serde_json::from_value(response)?)
+    }
+
+    fn action(&self, action: Value) -> anyhow::Result<()> {
+        self.request(json!({ "Action": action }))?;
+        Ok(())
+    }
+}
+
+impl Compositor for Niri {
+    // Workspaces are scrolled through per output, so their position is used as id
+    fn workspaces(&self) -> anyhow::Result<Vec<Workspace>> {
+        let windows = self.query::<Vec<NiriWindow>>("Windows")?;
+
+        Ok(self
+            .query::<Vec<NiriWorkspace>>("Workspaces")?
+            .into_iter()
+            .map(|workspace| Workspace {
+                id: workspace.idx as i32,
+                windows: windows
+                    .iter()
+                    .filter(|window| window.workspace_id == Some(workspace.id))
+                    .count(),
+                name: workspace
+                    .name
+                    .unwrap_or_else(|| workspace.idx.to_string())
+                    .into(),
+                output: workspace.output.unwrap_or_default().into(),
+                focused: workspace.is_focused,
+                visible: workspace.is_active,
+                urgent: workspace.is_urgent,
+            })
+            .collect())
+    }
+
+    // Positions refer to workspaces of the focused output
+    fn switch_workspace(&self, id: i32) -> anyhow::Result<()> {
+        self.action(json!({ "FocusWorkspace": { "reference": { "Index": id } } }))
+    }
+
+    // Every output has its own positions, so the workspace is switched to by its unique id
+    fn switch_workspace_on(&self, id: i32, output: &str) -> anyhow::Result<()> {
+        let workspace = self
+            .query::<Vec<NiriWorkspace>>("Workspaces")?
+            .into_iter()
+            .find(|workspace| {
+                workspace.idx as i32 == id && workspace.output.as_deref().unwrap_or("") == output
+            })
+            .ok_or_else(|| anyhow::anyhow!("Workspace not found"))?;
+        self.action(json!({ "FocusWorkspace": { "reference": { "Id": workspace.id } } }))
+    }
+
+    fn switch_workspace_name(&self, name: &str) -> anyhow::Result<()> {
+        self.action(json!({ "FocusWorkspace": { "reference": { "Name": name } } }))
+    }
+
+    fn outputs(&self) -> anyhow::Result<Vec<Box<str>>> {
+        let mut outputs: Vec<_> = self
+            .query::<HashMap<String, NiriOutput>>("Outputs")?
+            .into_iter()
+            .filter_map(|(name, output)| Some((output.logical?, name)))
+            .collect();
+        outputs.sort_by_key(|(logical, _)| (logical.x, logical.y));
+        Ok(outputs.into_iter().map(|(_, name)| name.into()).collect())
+    }
+
+    fn focused_window(&self) -> anyhow::Result<Option<Window>> {
+        Ok(self
+            .query::<Option<NiriWindow>>("FocusedWindow")?
+            .map(|window| Window {
+                title: window.title.unwrap_or_default().into(),
+                app_id: window.app_id.unwrap_or_default().into(),
+            }))
+    }
+
+    fn keyboard_layout(&self) -> anyhow::Result<Box<str>> {
+        let layouts = self.query::<KeyboardLayouts>("KeyboardLayouts")?;
+        layouts
+            .names
+            .get(layouts.current_idx as usize)
+            .map(|name| name.as_str().into())
+            .ok_or_else(|| anyhow::anyhow!("No keyboard layout found"))
+    }
+
+    fn switch_keyboard_layout(&self, next: bool) -> anyhow::Result<()> {
+        let layout = match next {
+            true => "Next",
+            false => "Prev",
+        };
+        self.action(json!({ "SwitchLayout": { "layout": layout } }))
+    }
+
+    /// Every line of the event stream is an event, all of them are treated as a change
+    fn listen(&self, tx: &broadcast::Sender<()>) -> anyhow::Result<()> {
+        let mut stream = UnixStream::connect(&self.socket)?;
+        writeln!(stream, "{}", json!("EventStream"))?;
+
+        for line in BufReader::new(stream).lines() {
+            line?;
+            _ = tx.send(());
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        os::unix::net::UnixListener,
+        sync::{Arc, Mutex},
+        thread,
+    };
+
+    // Replies recorded from niri with two outputs
+    const WORKSPACES: &str = r#"{"Ok":{"Workspaces":[{"id":1,"idx":1,"name":null,"output":"DP-1","is_active":true,"is_focused":true,"active_window_id":10},{"id":2,"idx":2,"name":"chat","output":"DP-1","is_active":false,"is_focused":false,"active_window_id":null},{"id":5,"idx":1,"name":null,"output":"HDMI-A-1","is_active":false,"is_focused":false,"active_window_id":null},{"id":6,"idx":2,"name":null,"output":"HDMI-A-1","is_active":true,"is_focused":false,"active_window_id":11}]}}"#;
+    const WINDOWS: &str = r#"{"Ok":{"Windows":[{"id":10,"title":"foot","app_id":"foot","pid":1200,"workspace_id":1,"is_focused":true,"is_floating":false},{"id":11,"title":"Firefox","app_id":"firefox","pid":1300,"workspace_id":6,"is_focused":false,"is_floating":false},{"id":12,"title":"htop","app_id":"foot","pid":1400,"workspace_id":1,"is_focused":false,"is_floating":false}]}}"#;
+    const OUTPUTS: &str = r#"{"Ok":{"Outputs":{"HDMI-A-1":{"name":"HDMI-A-1","make":"Dell","model":"U2415","logical":{"x":0,"y":0,"width":1920,"height":1200,"scale":1.0,"transform":"Normal"}},"DP-1":{"name":"DP-1","make":"LG","model":"27GL850","logical":{"x":1920,"y":0,"width":2560,"height":1440,"scale":1.0,"transform":"Normal"}},"eDP-1":{"name":"eDP-1","make":"BOE","model":"0x0BCA","logical":null}}}}"#;
+    const EVENTS: [&str; 3] = [
+        r#"{"Ok":"Handled"}"#,
+        r#"{"WorkspaceActivated":{"id":6,"focused":true}}"#,
+        r#"{"WindowFocusChanged":{"id":11}}"#,
+    ];
+
+    /// Serves the recorded replies on a socket in the temp directory, actions are collected
+    fn fake_niri(name: &str) -> (PathBuf, Arc<Mutex<Vec<Value>>>) {
+        let socket = std::env::temp_dir().join(format!("{name}-{}.sock", std::process::id()));
+        _ = std::fs::remove_file(&socket);
+        let listener = UnixListener::bind(&socket).unwrap();
+        let actions = Arc::<Mutex<Vec<Value>>>::default();
+
+        let received = Arc::clone(&actions);
+        thread::spawn(move || {
+            for mut stream in listener.incoming().map_while(Result::ok) {
+                let mut request = String::new();
+                BufReader::new(&stream).read_line(&mut request).unwrap();
+                let request: Value = serde_json::from_str(&request).unwrap();
+
+                let replies = match request.as_str() {
+                    Some("Workspaces") => vec![WORKSPACES],
+                    Some("Windows") => vec![WINDOWS],
+                    Some("Outputs") => vec![OUTPUTS],
+                    Some("EventStream") => EVENTS.to_vec(),
+                    _ => {
+                        received.lock().unwrap().push(request);
+                        vec![EVENTS[0]]
+                    }
+                };
+                for reply in replies {
+                    writeln!(stream, "{reply}").unwrap();
+                }
+            }
+        });
+
+        (socket, actions)
+    }
+
+    #[test]
+    fn recorded_replies() {
+        let (socket, actions) = fake_niri("niri-recorded-replies");
+        let niri = Niri::new(socket.clone());
+
+        let workspaces = niri.workspaces().unwrap();
+        let summary = workspaces
+            .iter()
+            .map(|workspace| {
+                (
+                    workspace.id,
+                    &*workspace.name,
+                    &*workspace.output,
+                    workspace.windows,
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            [
+                (1, "1", "DP-1", 2),
+                (2, "chat", "DP-1", 0),
+                (1, "1", "HDMI-A-1", 0),
+                (2, "2", "HDMI-A-1", 1),
+            ]
+        );
+        assert!(workspaces[0].focused && workspaces[3].visible && !workspaces[3].focused);
+
+        // Disabled outputs are left out
+        assert_eq!(
+            niri.outputs().unwrap(),
+            [Box::from("HDMI-A-1"), Box::from("DP-1")]
+        );
+
+        niri.switch_workspace_on(2, "HDMI-A-1").unwrap();
+        assert_eq!(
+            *actions.lock().unwrap(),
+            [json!({ "Action": { "FocusWorkspace": { "reference": { "Id": 6 } } } })]
+        );
+
+        // Stops once the stream is closed, after the reply and both events
+        let tx = broadcast::Sender::new(EVENTS.len());
+        let mut rx = tx.subscribe();
+        niri.listen(&tx).unwrap();
+        let mut changes = 0;
+        while rx.try_recv().is_ok() {
+            changes += 1;
+        }
+        assert_eq!(changes, EVENTS.len());
+
+        _ = std::fs::remove_file(socket);
+    }
+}
@@ -0,0 +1,428 @@
+use super::{Compositor, Window, Workspace};
+use log::warn;
+use protocols::{
+    control::{
+        zriver_command_callback_v1::{self, ZriverCommandCallbackV1},
+        zriver_control_v1::ZriverControlV1,
+    },
+    status::{
+        zriver_output_status_v1::{self, ZriverOutputStatusV1},
+        zriver_seat_status_v1::{self, ZriverSeatStatusV1},
+        zriver_status_manager_v1::ZriverStatusManagerV1,
+    },
+};
+use std::{
+    sync::{Arc, Mutex},
+    thread,
+};
+use tokio::sync::broadcast::{self, error::RecvError};
+use wayland_client::{
+    globals::{registry_queue_init, GlobalListContents},
+    protocol::{wl_output, wl_registry, wl_seat},
+    Connection, Dispatch, Proxy, QueueHandle,
+};
+
+#[allow(
+    non_upper_case_globals,
+    non_camel_case_types,
+    unused_imports,
+    clippy::all
+)]
+mod protocols {
+    pub mod status {
+        use wayland_client;
+        use wayland_client::protocol::*;
+
+        pub mod __interfaces {
+            use wayland_client::protocol::__interfaces::*;
+            wayland_scanner::generate_interfaces!("protocols/river-status-unstable-v1.xml");
+        }
+        use self::__interfaces::*;
+
+        wayland_scanner::generate_client_code!("protocols/river-status-unstable-v1.xml");
+    }
+
+    pub mod control {
+        use wayland_client;
+        use wayland_client::protocol::*;
+
+        pub mod __interfaces {
+            use wayland_client::protocol::__interfaces::*;
+            wayland_scanner::generate_interfaces!("protocols/river-control-unstable-v1.xml");
+        }
+        use self::__interfaces::*;
+
+        wayland_scanner::generate_client_code!("protocols/river-control-unstable-v1.xml");
+    }
+}
+
+struct Output {
+    // Registry name, used to forget the output when it's removed
+    global: u32,
+    output: wl_output::WlOutput,
+    status: ZriverOutputStatusV1,
+    name: Box<str>,
+    focused_tags: u32,
+    view_tags: Vec<u32>,
+    urgent_tags: u32,
+}
+
+#[derive(Default)]
+struct Status {
+    workspaces: Vec<Workspace>,
+    title: Box<str>,
+    mode: Box<str>,
+}
+
+/// Receives status events on its own connection, river has no `done` event so changes are
+/// published after every dispatched batch
+struct Dispatcher {
+    status_manager: ZriverStatusManagerV1,
+    outputs: Vec<Output>,
+    focused_output: Option<wl_output::WlOutput>,
+    title: Box<str>,
+    mode: Box<str>,
+    status: Arc<Mutex<Status>>,
+    tx: broadcast::Sender<()>,
+}
+
+impl Dispatcher {
+    fn add_output(
+        &mut self,
+        registry: &wl_registry::WlRegistry,
+        global: u32,
+        version: u32,
+        qh: &QueueHandle<Self>,
+    ) {
+        let output = registry.bind::<wl_output::WlOutput, _, _>(global, version.min(4), qh, ());
+        let status = self
+            .status_manager
+            .get_river_output_status(&output, qh, output.clone());
+        self.outputs.push(Output {
+            global,
+            output,
+            status,
+            name: "".into(),
+            focused_tags: 0,
+            view_tags: Vec::new(),
+            urgent_tags: 0,
+        });
+    }
+
+    /// Tags are shown like workspaces when they're focused, occupied or urgent
+    fn publish(&self) {
+        let workspaces = self
+            .outputs
+            .iter()
+            .flat_map(|output| {
+                let focused = self.focused_output.as_ref() == Some(&output.output);
+                let occupied = output.view_tags.iter().fold(0, |tags, view| tags | view);
+                let shown = output.focused_tags | occupied | output.urgent_tags;
+
+                (0..32)
+                    .map(|tag| 1u32 << tag)
+                    .enumerate()
+                    .filter(move |(_, mask)| shown & mask != 0)
+                    .map(move |(i, mask)| Workspace {
+                        id: i as i32 + 1,
+                        name: (i + 1).to_string().into(),
+                        output: output.name.clone(),
+                        focused: focused && output.focused_tags & mask != 0,
+                        visible: output.focused_tags & mask != 0,
+                        urgent: output.urgent_tags & mask != 0,
+                        windows: output
+                            .view_tags
+                            .iter()
+                            .filter(|tags| *tags & mask != 0)
+                            .count(),
+                    })
+            })
+            .collect();
+
+        *self.status.lock().unwrap() = Status {
+            workspaces,
+            title: self.title.clone(),
+            mode: self.mode.clone(),
+        };
+        _ = self.tx.send(());
+    }
+}
+
+/// Tags and focused view through river's status protocol, tags are switched with river commands
+pub struct River {
+    conn: Connection,
+    qh: QueueHandle<Dispatcher>,
+    // Arguments of concurrent commands must not interleave
+    control: Mutex<ZriverControlV1>,
+    seat: wl_seat::WlSeat,
+    status: Arc<Mutex<Status>>,
+    tx: broadcast::Sender<()>,
+}
+
+impl River {
+    pub fn new() -> anyhow::Result<Self> {
+        let conn = Connection::connect_to_env()?;
+        let (globals, mut queue) = registry_queue_init::<Dispatcher>(&conn)?;
+        let qh = queue.handle();
+        let status_manager: ZriverStatusManagerV1 = globals.bind(&qh, 1..=4, ())?;
+        let control = globals.bind(&qh, 1..=1, ())?;
+        let seat: wl_seat::WlSeat = globals.bind(&qh, 1..=1, ())?;
+        status_manager.get_river_seat_status(&seat, &qh, ());
+
+        let status = Arc::default();
+        let tx = broadcast::Sender::new(1);
+        let mut dispatcher = Dispatcher {
+            status_manager,
+            outputs: Vec::new(),
+            focused_output: None,
+            title: "".into(),
+            mode: "".into(),
+            status: Arc::clone(&status),
+            tx: tx.clone(),
+        };
+        globals.contents().with_list(|list| {
+            list.iter()
+                .filter(|global| global.interface == wl_output::WlOutput::interface().name)
+                .for_each(|global| {
+                    dispatcher.add_output(globals.registry(), global.name, global.version, &qh)
+                });
+        });
+        queue.roundtrip(&mut dispatcher)?;
+        dispatcher.publish();
+
+        thread::spawn(move || loop {
+            if let Err(e) = queue.blocking_dispatch(&mut dispatcher) {
+                warn!("Lost connection to river\n {}", e);
+                return;
+            }
+            dispatcher.publish();
+        });
+
+        Ok(Self {
+            conn,
+            qh,
+            control: Mutex::new(control),
+            seat,
+            status,
+            tx,
+        })
+    }
+
+    fn run_command(&self, args: &[&str]) -> anyhow::Result<()> {
+        let control = self.control.lock().unwrap();
+        args.iter()
+            .for_each(|arg| control.add_argument(arg.to_string()));
+        control.run_command(&self.seat, &self.qh, ());
+        Ok(self.conn.flush()?)
+    }
+}
+
+impl Compositor for River {
+    fn workspaces(&self) -> anyhow::Result<Vec<Workspace>> {
+        Ok(self.status.lock().unwrap().workspaces.clone())
+    }
+
+    // Tags are switched on the focused output
+    fn switch_workspace(&self, id: i32) -> anyhow::Result<()> {
+        if !(1..=32).contains(&id) {
+            return Err(anyhow::anyhow!("River only has 32 tags"));
+        }
+        self.run_command(&["set-focused-tags", &(1u32 << (id - 1)).to_string()])
+    }
+
+    // Every output has the same tags, its output is focused first so the right one changes
+    fn switch_workspace_on(&self, id: i32, output: &str) -> anyhow::Result<()> {
+        if !output.is_empty() {
+            self.run_command(&["focus-output", output])?;
+        }
+        self.switch_workspace(id)
+    }
+
+    fn switch_workspace_name(&self, name: &str) -> anyhow::Result<()> {
+        self.switch_workspace(name.parse()?)
+    }
+
+    // River doesn't report app ids
+    fn focused_window(&self) -> anyhow::Result<Option<Window>> {
+        let title = self.status.lock().unwrap().title.clone();
+        Ok((!title.is_empty()).then(|| Window {
+            title,
+            app_id: "".into(),
+        }))
+    }
+
+    fn binding_mode(&self) -> anyhow::Result<Box<str>> {
+        let mode = self.status.lock().unwrap().mode.clone();
+        Ok(match &*mode {
+            "" | "normal" => "default".into(),
+            _ => mode,
+        })
+    }
+
+    fn listen(&self, tx: &broadcast::Sender<()>) -> anyhow::Result<()> {
+        let mut rx = self.tx.subscribe();
+        loop {
+            match rx.blocking_recv() {
+                Err(RecvError::Closed) => return Ok(()),
+                _ => {
+                    _ = tx.send(());
+                }
+            }
+        }
+    }
+}
+
+impl Dispatch<wl_registry::WlRegistry, GlobalListContents> for Dispatcher {
+    fn event(
+        state: &mut Self,
+        proxy: &wl_registry::WlRegistry,
+        event: wl_registry::Event,
+        _data: &GlobalListContents,
+        _conn: &Connection,
+        qh: &QueueHandle<Self>,
+    ) {
+        match event {
+            wl_registry::Event::Global {
+                name,
+                interface,
+                version,
+            } if interface == wl_output::WlOutput::interface().name => {
+                state.add_output(proxy, name, version, qh)
+            }
+            wl_registry::Event::GlobalRemove { name } => state.outputs.retain(|output| {
+                if output.global == name {
+                    output.status.destroy();
+                }
+                output.global != name
+            }),
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<wl_output::WlOutput, ()> for Dispatcher {
+    fn event(
+        state: &mut Self,
+        proxy: &wl_output::WlOutput,
+        event: wl_output::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        if let wl_output::Event::Name { name } = event {
+            state
+                .outputs
+                .iter_mut()
+                .filter(|output| &output.output == proxy)
+                .for_each(|output| output.name = name.as_str().into());
+        }
+    }
+}
+
+impl Dispatch<ZriverOutputStatusV1, wl_output::WlOutput> for Dispatcher {
+    fn event(
+        state: &mut Self,
+        _proxy: &ZriverOutputStatusV1,
+        event: zriver_output_status_v1::Event,
+        data: &wl_output::WlOutput,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        let Some(output) = state
+            .outputs
+            .iter_mut()
+            .find(|output| &output.output == data)
+        else {
+            return;
+        };
+
+        match event {
+            zriver_output_status_v1::Event::FocusedTags { tags } => output.focused_tags = tags,
+            zriver_output_status_v1::Event::ViewTags { tags } => {
+                output.view_tags = tags
+                    .chunks_exact(4)
+                    .map(|bytes| u32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+                    .collect();
+            }
+            zriver_output_status_v1::Event::UrgentTags { tags } => output.urgent_tags = tags,
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<ZriverSeatStatusV1, ()> for Dispatcher {
+    fn event(
+        state: &mut Self,
+        _proxy: &ZriverSeatStatusV1,
+        event: zriver_seat_status_v1::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        match event {
+            zriver_seat_status_v1::Event::FocusedOutput { output } => {
+                state.focused_output = Some(output)
+            }
+            zriver_seat_status_v1::Event::UnfocusedOutput { output } => {
+                if state.focused_output.as_ref() == Some(&output) {
+                    state.focused_output = None;
+                }
+            }
+            zriver_seat_status_v1::Event::FocusedView { title } => state.title = title.into(),
+            zriver_seat_status_v1::Event::Mode { name } => state.mode = name.into(),
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<ZriverCommandCallbackV1, ()> for Dispatcher {
+    fn event(
+        _state: &mut Self,
+        _proxy: &ZriverCommandCallbackV1,
+        event: zriver_command_callback_v1::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        if let zriver_command_callback_v1::Event::Failure { failure_message } = event {
+            warn!("River command failed\n {}", failure_message);
+        }
+    }
+}
+
+// Neither the status manager, control nor seat have events that matter here
+impl Dispatch<ZriverStatusManagerV1, ()> for Dispatcher {
+    fn event(
+        _state: &mut Self,
+        _proxy: &ZriverStatusManagerV1,
+        _event: <ZriverStatusManagerV1 as Proxy>::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+impl Dispatch<ZriverControlV1, ()> for Dispatcher {
+    fn event(
+        _state: &mut Self,
+        _proxy: &ZriverControlV1,
+        _event: <ZriverControlV1 as Proxy>::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+impl Dispatch<wl_seat::WlSeat, ()> for Dispatcher {
+    fn event(
+        _state: &mut Self,
+        _proxy: &wl_seat::WlSeat,
+        _event: wl_seat::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+    }
+}
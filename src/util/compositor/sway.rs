@@ -0,0 +1,136 @@
+use super::{Compositor, Window, Workspace};
+use swayipc::{Connection, EventType, Node, NodeType};
+use tokio::sync::broadcast;
+
+pub struct Sway;
+
+/// Number of windows in a container, including nested ones
+fn windows(node: &Node) -> usize {
+    let is_window = |node: &Node| node.nodes.is_empty() && node.floating_nodes.is_empty();
+    node.nodes
+        .iter()
+        .chain(&node.floating_nodes)
+        .map(|child| if is_window(child) { 1 } else { windows(child) })
+        .sum()
+}
+
+fn run_command(command: &str) -> anyhow::Result<()> {
+    for outcome in Connection::new()?.run_command(command)? {
+        outcome?;
+    }
+
+    Ok(())
+}
+
+impl Compositor for Sway {
+    fn workspaces(&self) -> anyhow::Result<Vec<Workspace>> {
+        let mut connection = Connection::new()?;
+        let tree = connection.get_tree()?;
+
+        Ok(connection
+            .get_workspaces()?
+            .into_iter()
+            .map(|workspace| Workspace {
+                id: workspace.num,
+                windows: tree
+                    .find_as_ref(|node| {
+                        node.node_type == NodeType::Workspace
+                            && node.name.as_deref() == Some(&workspace.name)
+                    })
+                    .map_or(0, windows),
+                name: workspace.name.into(),
+                output: workspace.output.into(),
+                focused: workspace.focused,
+                visible: workspace.visible,
+                urgent: workspace.urgent,
+            })
+            .collect())
+    }
+
+    fn switch_workspace(&self, id: i32) -> anyhow::Result<()> {
+        run_command(&format!("workspace number {id}"))
+    }
+
+    fn switch_workspace_name(&self, name: &str) -> anyhow::Result<()> {
+        run_command(&format!("workspace \"{name}\""))
+    }
+
+    fn outputs(&self) -> anyhow::Result<Vec<Box<str>>> {
+        let mut outputs = Connection::new()?.get_outputs()?;
+        outputs.retain(|output| output.active);
+        outputs.sort_by_key(|output| (output.rect.x, output.rect.y));
+        Ok(outputs
+            .into_iter()
+            .map(|output| output.name.into())
+            .collect())
+    }
+
+    fn focused_window(&self) -> anyhow::Result<Option<Window>> {
+        let tree = Connection::new()?.get_tree()?;
+        // A focused workspace means no window is focused
+        Ok(tree
+            .find_focused(|node| node.focused)
+            .filter(|node| matches!(node.node_type, NodeType::Con | NodeType::FloatingCon))
+            .map(|node| Window {
+                app_id: node
+                    .app_id
+                    .or_else(|| {
+                        node.window_properties
+                            .and_then(|properties| properties.class)
+                    })
+                    .unwrap_or_default()
+                    .into(),
+                title: node.name.unwrap_or_default().into(),
+            }))
+    }
+
+    fn keyboard_layout(&self) -> anyhow::Result<Box<str>> {
+        Connection::new()?
+            .get_inputs()?
+            .into_iter()
+            .filter(|input| input.input_type == "keyboard")
+            .find_map(|input| input.xkb_active_layout_name)
+            .map(Into::into)
+            .ok_or_else(|| anyhow::anyhow!("No keyboard found"))
+    }
+
+    fn switch_keyboard_layout(&self, next: bool) -> anyhow::Result<()> {
+        let command = match next {
+            true => "input type:keyboard xkb_switch_layout next",
+            false => "input type:keyboard xkb_switch_layout prev",
+        };
+        run_command(command)
+    }
+
+    fn scratchpad(&self) -> anyhow::Result<usize> {
+        let tree = Connection::new()?.get_tree()?;
+        Ok(tree
+            .find_as_ref(|node| node.name.as_deref() == Some("__i3_scratch"))
+            .map_or(0, windows))
+    }
+
+    fn show_scratchpad(&self) -> anyhow::Result<()> {
+        run_command("scratchpad show")
+    }
+
+    fn binding_mode(&self) -> anyhow::Result<Box<str>> {
+        Ok(Connection::new()?.get_binding_state()?.into())
+    }
+
+    fn listen(&self, tx: &broadcast::Sender<()>) -> anyhow::Result<()> {
+        let events = Connection::new()?.subscribe([
+            EventType::Workspace,
+            EventType::Output,
+            EventType::Window,
+            EventType::Input,
+            EventType::Mode,
+        ])?;
+
+        for event in events {
+            event?;
+            _ = tx.send(());
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,109 @@
+use lazy_static::lazy_static;
+use log::warn;
+use std::{
+    sync::{mpsc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+use tokio::sync::broadcast;
+use zbus::{
+    blocking::{Connection, MessageIterator},
+    MatchRule, Message,
+};
+
+const MIN_BACKOFF: Duration = Duration::from_millis(500);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+// Runs lasting this long worked, so the next restart is quick again
+const STABLE_RUN: Duration = Duration::from_secs(60);
+
+lazy_static! {
+    // Used for method calls, listeners open their own connection
+    static ref SESSION: Mutex<Option<Connection>> = Mutex::new(None);
+}
+
+/// Runs `f` with the shared session bus connection, connecting first if there is none
+pub fn with_session<T>(f: impl FnOnce(&Connection) -> anyhow::Result<T>) -> anyhow::Result<T> {
+    let mut session = SESSION.lock().unwrap();
+    if session.is_none() {
+        session.replace(Connection::session()?);
+    }
+
+    f(session.as_ref().unwrap())
+}
+
+/// Messages matching any of `rules`, merged so a single thread can wait on them
+pub fn messages<'a>(
+    connection: &Connection,
+    rules: impl IntoIterator<Item = MatchRule<'a>>,
+) -> anyhow::Result<mpsc::Receiver<zbus::Result<Message>>> {
+    let streams = rules
+        .into_iter()
+        .map(|rule| MessageIterator::for_match_rule(rule, connection, None))
+        .collect::<zbus::Result<Vec<_>>>()?;
+
+    let (message_tx, message_rx) = mpsc::channel();
+    streams.into_iter().for_each(|mut messages| {
+        let message_tx = message_tx.clone();
+        thread::spawn(move || {
+            // Stops once the receiving end is gone
+            _ = messages.try_for_each(|message| message_tx.send(message));
+        });
+    });
+
+    Ok(message_rx)
+}
+
+/// Restarts `run` with backoff until nobody is listening anymore,
+/// `run` should return once the connection is lost or `tx` has no receivers left
+pub fn listen(
+    name: &str,
+    tx: broadcast::Sender<()>,
+    run: impl Fn(&broadcast::Sender<()>) -> anyhow::Result<()>,
+) {
+    let mut backoff = MIN_BACKOFF;
+
+    while tx.receiver_count() > 0 {
+        let started = Instant::now();
+        let result = run(&tx);
+        if started.elapsed() >= STABLE_RUN {
+            backoff = MIN_BACKOFF;
+        }
+        if tx.receiver_count() == 0 {
+            return;
+        }
+
+        // Returning while still listened to means the connection went away, e.g. the bus closed
+        match result {
+            Ok(()) => warn!("{name} stopped, restarting in {:?}", backoff),
+            Err(e) => warn!("{name} failed, retrying in {:?}\n {}", backoff, e),
+        }
+        thread::sleep(backoff);
+        backoff = (backoff * 2).min(MAX_BACKOFF);
+    }
+}
+
+/// Connection to a stand-in service serving `iface` at `path`, along with the service side
+#[cfg(test)]
+pub fn peer_to_peer(
+    path: &'static str,
+    iface: impl zbus::object_server::Interface,
+) -> (Connection, Connection) {
+    use std::os::unix::net::UnixStream;
+    use zbus::{blocking::connection::Builder, Guid};
+
+    let (server_stream, client_stream) = UnixStream::pair().unwrap();
+    // Both sides have to take part in the handshake
+    let server = thread::spawn(move || {
+        Builder::unix_stream(server_stream)
+            .server(Guid::generate())
+            .unwrap()
+            .p2p()
+            .serve_at(path, iface)
+            .unwrap()
+            .build()
+            .unwrap()
+    });
+    let client = Builder::unix_stream(client_stream).p2p().build().unwrap();
+
+    (client, server.join().unwrap())
+}
@@ -19,11 +19,22 @@ pub fn combine_images(images: &[&image::DynamicImage]) -> image::DynamicImage {
     image::DynamicImage::ImageRgba8(new_img)
 }
 
+/// Cuts off text longer than `max_length` characters with an ellipsis
+pub fn truncate(text: &str, max_length: Option<usize>) -> String {
+    match max_length {
+        Some(max_length) if text.chars().count() > max_length => {
+            text.chars().take(max_length).collect::<String>() + "…"
+        }
+        _ => text.to_string(),
+    }
+}
+
 pub const TOML_STRING: &str = r#"
 unkown = "N/A" # Default value for unknown commands
 background = [20, 15, 33, 1] # Background color as RGB value
 topbar = true # true for bar at top of the screen, false for bar at bottom of the screen
 height = 40 # Height of the bar
+audio_backend = "Auto" # "Auto", "Pulse" or "PipeWire", Auto uses PipeWire when it is running
 
 # Font settings
 
@@ -32,17 +43,93 @@ height = 40 # Height of the bar
 # Modules are individual components of the bar that display different information.
 # Each module has a `command` which determines what information it displays,
 
+# Alerts
+
+# Every module can take a list of alerts which are checked whenever its numeric output changes.
+# Each alert takes a condition ({ Above = value }, { Below = value } or { Equal = value }), a summary
+# and optionally a body (with "%s" as a placeholder for the value), an urgency ("Low", "Normal" or "Critical"),
+# a cooldown in milliseconds during which the alert won't be repeated (5 minutes by default),
+# and a command which is run instead of sending a desktop notification
+# (with "%s", "%u" and "%b" as placeholders for the value, summary and body).
+
+# [[modules.right]]
+# command.Cpu = { interval = 5000, formatting = "󰍛 %s%" }
+# alerts = [{ condition = { Above = 90 }, summary = "CPU usage at %s%", urgency = "Critical", cooldown = 60000 }]
+
 # Workspaces Module
 
 # This module displays the active and inactive workspaces. It takes two arguments:
 # the icon for the active window and the icon for the inactive window.
+# Every bar only shows workspaces of its own output. Optionally it takes the icon for workspaces
+# visible on another output (active icon by default), whether to show workspaces of all outputs instead
+# and the separator between outputs ("| " by default).
+# Inactive workspaces with an urgent window use the `urgent` icon (inactive icon by default) and the module
+# can be styled with `workspaces.urgent` while there are any. `%n` in icons is replaced with the number of windows.
+# Clicking a workspace switches to it and scrolling cycles through workspaces of the output.
 
 # Available for these compositors:
 # - Hyprland
+# - Sway
+# - River, where occupied, focused and urgent tags are shown as workspaces
+# - niri
+# - Any compositor implementing ext-workspace-v1 (e.g. labwc, wayfire) or its older zext_workspace_v1 draft
+#   Neither tells which output is focused, so workspaces are only ever shown as visible
 
 [[modules.left]]
 command.Workspaces = { active = " ", inactive = " " }
 
+# Persistant Workspaces Module
+
+# This module always displays the same workspaces, even if they don't exist yet. `workspaces` is either
+# their count (10 by default) or a list of their names, e.g. named sway workspaces or Hyprland "special:" ones.
+# Every workspace is in one of these states: active, visible, urgent, occupied or empty. Icons are looked up
+# by state first, then by workspace name, visible falls back to active and the others fall back to inactive.
+# `%n` in icons is replaced with the number of windows on the workspace.
+# Workspaces are styled with `persistant_workspaces#<state>` selectors or by index, e.g. `persistant_workspaces#0`.
+# Every bar only shows the state of workspaces on its own output unless `all_outputs` is set.
+
+# [[modules.left]]
+# command.PersistantWorkspaces = { workspaces = 5, active = "A", occupied = "O", empty = "E" }
+# command.PersistantWorkspaces = { workspaces = ["web", "code", "special:scratch"], active = "A" }
+
+# Window Title Module
+
+# This module displays the title of the focused window. Optionally it takes the formatting (where "%s" is
+# the title and "%a" the app_id or X11 class), the maximum length after which the title is cut off with an ellipsis
+# and rewrite rules. The first rule whose regex matches the title replaces it, "$1" refers to the first capture group.
+# Rules can be limited to windows with given app_id.
+
+# [[modules.center]]
+# command.WindowTitle = { max_length = 50, rewrite = [{ app_id = "firefox", pattern = "(.*) — Mozilla Firefox", replacement = "$1" }] }
+
+# Taskbar Module
+
+# This module lists open windows, on wlroots based compositors (and others implementing wlr-foreign-toplevel-management).
+# Every bar only shows windows on its own output unless `all_outputs` is set. Windows are shown with their icon
+# and optionally their title, which can be cut off after `max_length` characters.
+# Left click activates a window or minimizes it when it's already active, right click toggles minimizing
+# and middle click closes it. Windows can be styled with `taskbar.active`, `taskbar.minimized` and `taskbar.maximized`.
+
+# [[modules.left]]
+# command.Taskbar = { icons = true, titles = true, max_length = 20, icon_size = 20, spacing = 5 }
+
+# Mode Module
+
+# This module displays the current sway or river binding mode or Hyprland submap and is hidden in the default mode.
+# It takes the formatting and optionally the text displayed for each mode, every mode can be styled with
+# its name as class, e.g. `mode.resize`. Hyprland doesn't report the submap until it changes for the first time.
+
+# [[modules.left]]
+# command.Mode = { formatting = "%s", modes = { resize = "resize" } }
+
+# Scratchpad Module
+
+# This module displays the number of windows in the sway scratchpad or Hyprland special workspaces
+# and is hidden while there are none. It takes the formatting and optionally whether clicking it shows the scratchpad.
+
+# [[modules.right]]
+# command.Scratchpad = { formatting = "scratchpad %s", show_on_click = true }
+
 # Custom Module
 
 # This module allows for custom commands. It takes four arguments: the command to execute,
@@ -85,30 +172,119 @@ command.Cpu = { interval = 5000, formatting = "󰍛 %s%" }
 [[modules.right]]
 command.Memory = { memory_opts = "PercUsed", interval = 5000, formatting = "󰍛 %s%" }
 
+# Disk Module
+
+# This module displays usage of the filesystem mounted at the given path. It takes the path, the update
+# time in milliseconds, the formatting for the display (with "%s" as a placeholder for the value) and
+# optionally the disk option ("Used", "Free", "Total", "PercUsed", "PercFree", "Read" or "Write"),
+# the unit ("Auto", "B", "KiB", "MiB", "GiB" or "TiB") and the warning and critical thresholds
+# in percent of used space which can be styled with the "disk.warning" and "disk.critical" selectors.
+# "Read" and "Write" display the I/O rate of the device backing the path.
+
+# [[modules.right]]
+# command.Disk = { path = "/", interval = 60000, formatting = "󰋊 %s%", warning = 80, critical = 95 }
+
 # Audio Module
 
 # This module is designed to display the audio level. It takes two arguments:
 # the display format (where "%s" is a placeholder for the value and "%c" stands for icons), and an array of icons.
+# Optionally it takes the audio option ("Volume", "Description" of the default device or its active "Port")
+# and an icon used when the device is muted. Muted devices can be styled with the "audio.muted" selector.
+# Clicking the module opens a mixer listing applications playing audio, scrolling over a row changes its volume,
+# clicking it mutes it and clicking its output moves it to the next one. The mixer is styled with the "mixer",
+# "mixer.muted" and "mixer.sink" selectors.
 
 [[modules.right]]
-command.Audio = { formatting = "%c %s%", icons = ["", "", "󰕾", ""] }
+command.Audio = { formatting = "%c %s%", icons = ["", "", "󰕾", ""], muted_icon = "󰝟" }
+
+# Microphone Module
+
+# Same as the audio module but for the default input device, styled with the "microphone" selector.
+
+# [[modules.right]]
+# command.Microphone = { formatting = "%c %s%", icons = [""], muted_icon = "" }
+
+# Privacy Module
+
+# This module shows which applications are recording audio and hides itself when nothing is.
+# It takes the display format (where "%s" is a placeholder for the application names)
+# and optionally the separator between names (", " by default).
+
+# [[modules.right]]
+# command.Privacy = { formatting = " %s" }
+
+# Media Module
+
+# This module shows the track of the most recently active MPRIS media player and hides itself when there is none.
+# It takes the display format (where "%s" is a placeholder for the track and "%c" for the status icon),
+# and optionally the track format (where "%a", "%t" and "%b" are placeholders for artist, title and album),
+# the maximum length after which the track is truncated and icons for "playing", "paused" and "stopped".
+# Left click toggles playback, right click or scrolling down skips to the next track,
+# middle click or scrolling up goes back to the previous one. Statuses can be styled like "media.playing".
+
+# [[modules.center]]
+# command.Media = { formatting = "%c %s", track = "%a - %t", max_length = 40, status_icons = { playing = "", paused = "" } }
+
+# Keyboard Layout Module
+
+# This module shows the active keyboard layout. It takes the display format (where "%s" is a placeholder
+# for the layout) and optionally whether to show the xkb layout name (e.g. "us" for "English (US)") instead
+# of the full one and a table mapping full layout names to the text displayed for them, which takes precedence.
+# Layout names are looked up in the xkb rules under $XKB_CONFIG_ROOT (/usr/share/X11/xkb by default).
+# Left click or scrolling down switches to the next layout, right click or scrolling up to the previous one.
+
+# Available for these compositors:
+# - Hyprland
+# - Sway
+# - niri
+
+# [[modules.right]]
+# command.KeyboardLayout = { formatting = "󰌌 %s", short = true, layouts = { "English (US)" = "us", "Polish" = "pl" } }
+
+# Idle Inhibitor Module
+
+# This module prevents the screen from idling (locking, blanking or suspending) while activated, left click toggles it.
+# It takes the display format (where "%c" is a placeholder for the status icon), and optionally icons for
+# "activated" and "deactivated" and whether the state should persist across restarts.
+# Statuses can be styled like "idle_inhibitor.activated".
+
+# [[modules.right]]
+# command.IdleInhibitor = { formatting = "%c", status_icons = { activated = "", deactivated = "" }, persist = true }
+
+# Tray Module
+
+# This module shows StatusNotifierItem icons of running applications. Optionally it takes the size of the icons
+# and the spacing between them. Left click activates an application, middle click triggers its secondary action,
+# scrolling is passed to the application and right click opens its menu, which is styled with the "menu"
+# and "menu.disabled" selectors.
+
+# [[modules.right]]
+# command.Tray = { icon_size = 20, spacing = 10 }
 
 # Backlight Module
 
 # This module is designed to show the level of screen backlight. It requires two arguments:
 # the display format (where "%s" is a placeholder for the value and "%c" is a placeholder for icons), and an array of icons.
+# Optionally it takes the name of the device (first one found by default), whether to use the keyboard backlight
+# instead of the screen, and the percentage by which scrolling over the module changes brightness.
+# Clicking the keyboard backlight cycles through its levels.
 
 [[modules.right]]
 command.Backlight = { formatting = "%c %s%", icons = ["", "", "", "", "", "", "", "", ""] }
 
 # Battery Module
 
-# This module displays the battery status. It takes three arguments:
+# This module displays the battery status, combined across all batteries. It takes three arguments:
 # the update time in milliseconds, the formatting for the display (with "%s" as a placeholder
 # for the value and %c as a placeholder for icons), and an array of icons.
+# Optionally it takes the battery option ("Capacity", "Status", "Power" in watts or "TimeRemaining"),
+# the capacity in percent below which the battery is critical, and icons per status
+# ("charging", "discharging", "full", "not_charging" or "critical") which replace the regular icons.
+# Statuses can be styled with selectors like "battery.charging" or "battery.critical".
+# The module is refreshed immediately when the power adapter is plugged in or out.
 
 [[modules.right]]
-command.Battery = { interval = 5000, formatting = "%c %s%", icons = ["󰁺" ,"󰁺", "󰁻", "󰁼", "󰁽", "󰁾", "󰁿", "󰂀", "󰂁", "󰂂", "󰁹"] }
+command.Battery = { interval = 5000, formatting = "%c %s%", icons = ["󰁺" ,"󰁺", "󰁻", "󰁼", "󰁽", "󰁾", "󰁿", "󰂀", "󰂁", "󰂂", "󰁹"], status_icons = { charging = "󰂄" } }
 "#;
 
 pub const CSS_STRING: &str = r#"
@@ -130,6 +306,10 @@ audio {
     margin-right: 25px;
 }
 
+microphone {
+    margin-right: 25px;
+}
+
 cpu {
     margin-right: 25px;
 }
@@ -142,6 +322,10 @@ workspaces {
     margin-left: 35px;
 }
 
+disk {
+    margin-right: 25px;
+}
+
 network {
     margin-right: 25px;
 }
@@ -154,4 +338,41 @@ persistant_workspaces {
     letter-spacing: 10px;
     margin-left: 35px;
 }
+
+media {
+    margin-right: 25px;
+}
+
+privacy {
+    margin-right: 25px;
+}
+
+mixer {
+    margin: 5px 10px;
+}
+
+menu {
+    margin: 5px 10px;
+}
+
+taskbar {
+    margin-right: 10px;
+    padding: 0px 5px;
+}
+
+keyboard_layout {
+    margin-right: 25px;
+}
+
+mode {
+    margin-right: 25px;
+}
+
+scratchpad {
+    margin-right: 25px;
+}
+
+idle_inhibitor {
+    margin-right: 25px;
+}
 "#;
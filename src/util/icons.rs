@@ -0,0 +1,69 @@
+use image::{imageops::FilterType, DynamicImage};
+use lazy_static::lazy_static;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+lazy_static! {
+    static ref ICONS: Mutex<HashMap<Box<str>, Option<PathBuf>>> = Mutex::new(HashMap::new());
+}
+
+// Sizes closest to the bar height are checked first
+const SIZES: [&str; 7] = [
+    "32x32", "24x24", "48x48", "64x64", "22x22", "128x128", "256x256",
+];
+
+fn icon_dirs() -> Vec<PathBuf> {
+    let data_home = std::env::var("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .ok()
+        .or_else(|| dirs::home_dir().map(|home| home.join(".local/share")));
+    let data_dirs = std::env::var("XDG_DATA_DIRS")
+        .unwrap_or("/usr/local/share:/usr/share".into())
+        .split(':')
+        .map(PathBuf::from)
+        .collect::<Vec<_>>();
+
+    data_home
+        .into_iter()
+        .chain(data_dirs)
+        .map(|dir| dir.join("icons"))
+        .collect()
+}
+
+fn lookup(name: &str) -> Option<PathBuf> {
+    let path = Path::new(name);
+    if path.is_absolute() {
+        return path.exists().then(|| path.to_path_buf());
+    }
+
+    // Only png icons can be decoded, so themes shipping svg only won't be found
+    let file = format!("{name}.png");
+    icon_dirs()
+        .iter()
+        .flat_map(|dir| {
+            SIZES
+                .iter()
+                .map(move |size| dir.join("hicolor").join(size).join("apps").join(&file))
+        })
+        .chain(std::iter::once(Path::new("/usr/share/pixmaps").join(&file)))
+        .find(|path| path.exists())
+}
+
+/// Finds an application icon by name in the hicolor theme or pixmaps
+pub fn find_icon(name: &str) -> Option<PathBuf> {
+    ICONS
+        .lock()
+        .unwrap()
+        .entry(name.into())
+        .or_insert_with(|| lookup(name))
+        .clone()
+}
+
+pub fn load_icon(name: &str, size: u32) -> Option<DynamicImage> {
+    let path = find_icon(name)?;
+    let icon = image::open(path).ok()?;
+    Some(icon.resize_exact(size, size, FilterType::Triangle))
+}
@@ -1,19 +1,17 @@
-extern crate libpulse_binding as pulse;
-
-use hyprland::event_listener::EventListener;
+use super::{
+    audio,
+    compositor::{self, Compositor},
+    mpris, tray,
+};
 use inotify::{Inotify, WatchMask};
 use log::warn;
-use pulse::{
-    context::{subscribe::InterestMaskSet, Context, FlagSet as ContextFlagSet},
-    mainloop::threaded::Mainloop,
-};
 use serde::{Deserialize, Serialize};
 use std::{
     collections::HashMap,
+    os::fd::{AsRawFd, FromRawFd, OwnedFd},
     path::{Path, PathBuf},
-    thread::{self, JoinHandle},
+    thread,
 };
-use swayipc::EventType;
 use tokio::sync::broadcast;
 
 #[derive(Serialize, Deserialize, PartialEq)]
@@ -24,71 +22,17 @@ pub enum Trigger {
     VolumeChanged,
 }
 
-pub enum WorkspaceListener {
-    Hyprland(Box<EventListener>),
-    Sway(JoinHandle<()>),
-}
-
 pub struct WorkspaceListenerData {
     tx: broadcast::Sender<()>,
-    listener: WorkspaceListener,
+    compositor: &'static dyn Compositor,
 }
 
-unsafe impl Send for WorkspaceListenerData {}
-
 impl WorkspaceListenerData {
-    pub fn new() -> Result<Self, Box<dyn crate::Error>> {
-        let tx = broadcast::Sender::new(1);
-
-        let hyprland = std::env::var("HYPRLAND_INSTANCE_SIGNATURE");
-        let sway = std::env::var("SWAYSOCK");
-
-        let listener = match (hyprland.is_ok(), sway.is_ok()) {
-            (true, _) => {
-                let mut listener = EventListener::new();
-                {
-                    let tx = tx.clone();
-                    listener.add_workspace_destroy_handler(move |_| {
-                        _ = tx.send(());
-                    });
-                }
-
-                {
-                    let tx = tx.clone();
-                    listener.add_workspace_change_handler(move |_| {
-                        _ = tx.send(());
-                    });
-                }
-
-                {
-                    let tx = tx.clone();
-                    listener.add_active_monitor_change_handler(move |_| {
-                        _ = tx.send(());
-                    });
-                }
-
-                WorkspaceListener::Hyprland(Box::new(listener))
-            }
-            (_, true) => {
-                let listener = swayipc::Connection::new()?
-                    .subscribe([EventType::Workspace, EventType::Output])?;
-
-                let tx = tx.clone();
-                let handle = thread::spawn(move || {
-                    listener.for_each(|_| {
-                        _ = tx.send(());
-                    });
-                });
-
-                WorkspaceListener::Sway(handle)
-            }
-            _ => {
-                warn!("Unsupported compositor, disabling workspaces module");
-                return Err("No supported compositor found".into());
-            }
-        };
-
-        Ok(Self { tx, listener })
+    pub fn new() -> Option<Self> {
+        Some(Self {
+            tx: broadcast::Sender::new(1),
+            compositor: compositor::compositor()?,
+        })
     }
 }
 
@@ -112,11 +56,54 @@ impl FileListenerData {
     }
 }
 
+pub struct UeventListenerData {
+    store: Vec<(Box<str>, broadcast::Sender<()>)>,
+}
+
+impl UeventListenerData {
+    fn new() -> Self {
+        Self { store: Vec::new() }
+    }
+
+    fn socket() -> std::io::Result<OwnedFd> {
+        let fd = unsafe {
+            libc::socket(
+                libc::AF_NETLINK,
+                libc::SOCK_DGRAM | libc::SOCK_CLOEXEC,
+                libc::NETLINK_KOBJECT_UEVENT,
+            )
+        };
+        if fd < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+
+        let mut addr: libc::sockaddr_nl = unsafe { std::mem::zeroed() };
+        addr.nl_family = libc::AF_NETLINK as u16;
+        addr.nl_groups = 1; // Kernel uevent multicast group
+        let res = unsafe {
+            libc::bind(
+                fd.as_raw_fd(),
+                &addr as *const libc::sockaddr_nl as *const libc::sockaddr,
+                std::mem::size_of::<libc::sockaddr_nl>() as libc::socklen_t,
+            )
+        };
+        if res < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+
+        Ok(fd)
+    }
+}
+
 pub struct Listeners {
     file_listener: Option<FileListenerData>,
     time_listener: Option<Vec<TimeListenerData>>,
     workspace_listener: Option<WorkspaceListenerData>,
     volume_listener: Option<broadcast::Sender<()>>,
+    media_listener: Option<broadcast::Sender<()>>,
+    tray_listener: Option<broadcast::Sender<()>>,
+    uevent_listener: Option<UeventListenerData>,
 }
 
 impl Listeners {
@@ -124,8 +111,11 @@ impl Listeners {
         Self {
             file_listener: Some(FileListenerData::new()),
             time_listener: Some(Vec::new()),
-            workspace_listener: WorkspaceListenerData::new().ok(),
+            workspace_listener: WorkspaceListenerData::new(),
             volume_listener: Some(broadcast::Sender::new(1)),
+            media_listener: Some(broadcast::Sender::new(1)),
+            tray_listener: Some(broadcast::Sender::new(1)),
+            uevent_listener: Some(UeventListenerData::new()),
         }
     }
 
@@ -138,6 +128,9 @@ impl Listeners {
         let file_listener = self.file_listener.take();
         let mut workspace_listener = self.workspace_listener.take();
         let volume_listener = self.volume_listener.take();
+        let media_listener = self.media_listener.take();
+        let tray_listener = self.tray_listener.take();
+        let uevent_listener = self.uevent_listener.take();
 
         // TLDR: thread sorts listeners by interval, waits for the shortest interval sends the message
         // to the listeners whose interval has passed and resets the interval in a loop
@@ -191,11 +184,8 @@ impl Listeners {
                     return;
                 }
 
-                match workspace_listener.listener {
-                    WorkspaceListener::Hyprland(mut listener) => {
-                        let _ = listener.start_listener();
-                    }
-                    WorkspaceListener::Sway(_) => {}
+                if let Err(e) = workspace_listener.compositor.listen(&workspace_listener.tx) {
+                    warn!("Workspace listener stopped\n {}", e);
                 }
             }
         });
@@ -206,25 +196,80 @@ impl Listeners {
                     return;
                 }
 
-                let mut mainloop = Mainloop::new().unwrap();
-                let mut context = Context::new(&mainloop, "volume-change-listener").unwrap();
-                _ = context.connect(None, ContextFlagSet::NOFLAGS, None);
-                _ = mainloop.start();
-                loop {
-                    if context.get_state() == libpulse_binding::context::State::Ready {
-                        break;
-                    }
+                audio::listen(volume_listener);
+            }
+        });
+
+        thread::spawn(move || {
+            if let Some(media_listener) = media_listener {
+                if media_listener.receiver_count() == 0 {
+                    return;
                 }
 
-                context.set_subscribe_callback(Some(Box::new(move |_, _, _| {
-                    _ = volume_listener.send(());
-                })));
-                context.subscribe(InterestMaskSet::SINK, |_| {});
+                mpris::listen(media_listener);
+            }
+        });
+
+        thread::spawn(move || {
+            if let Some(tray_listener) = tray_listener {
+                if tray_listener.receiver_count() == 0 {
+                    return;
+                }
 
-                let mainloop = Box::new(mainloop);
-                let context = Box::new(context);
-                Box::leak(context);
-                Box::leak(mainloop);
+                tray::listen(tray_listener);
+            }
+        });
+
+        thread::spawn(move || {
+            if let Some(uevent_listener) = uevent_listener {
+                if uevent_listener.store.is_empty() {
+                    return;
+                }
+
+                let socket = match UeventListenerData::socket() {
+                    Ok(socket) => socket,
+                    Err(e) => {
+                        warn!("Failed to create uevent listener\n {}", e);
+                        return;
+                    }
+                };
+
+                loop {
+                    let mut buffer = [0u8; 8192];
+                    let len = unsafe {
+                        libc::recv(
+                            socket.as_raw_fd(),
+                            buffer.as_mut_ptr() as *mut libc::c_void,
+                            buffer.len(),
+                            0,
+                        )
+                    };
+                    if len < 0 {
+                        let e = std::io::Error::last_os_error();
+                        match e.raw_os_error() {
+                            // Interrupted, or events were dropped because the buffer overflowed
+                            Some(libc::EINTR | libc::EAGAIN | libc::ENOBUFS) => continue,
+                            _ => {
+                                warn!("Uevent listener stopped\n {}", e);
+                                return;
+                            }
+                        }
+                    }
+
+                    // Message is "action@devpath" followed by null separated KEY=VALUE pairs
+                    buffer[..len as usize]
+                        .split(|byte| *byte == 0)
+                        .filter_map(|field| field.strip_prefix(b"SUBSYSTEM="))
+                        .for_each(|subsystem| {
+                            uevent_listener
+                                .store
+                                .iter()
+                                .filter(|(name, _)| name.as_bytes() == subsystem)
+                                .for_each(|(_, tx)| {
+                                    _ = tx.send(());
+                                });
+                        });
+                }
             }
         });
     }
@@ -243,6 +288,29 @@ impl Listeners {
         rx
     }
 
+    /// Triggers on kernel uevents of given subsystem and falls back to polling at given interval
+    /// for values that change without emitting an event
+    pub fn new_uevent_listener(
+        &mut self,
+        subsystem: &str,
+        interval: u64,
+    ) -> broadcast::Receiver<()> {
+        let (tx, rx) = broadcast::channel(1);
+
+        self.time_listener.as_mut().unwrap().push(TimeListenerData {
+            tx: tx.clone(),
+            interval,
+            original_interval: interval,
+        });
+        self.uevent_listener
+            .as_mut()
+            .unwrap()
+            .store
+            .push((subsystem.into(), tx));
+
+        rx
+    }
+
     pub fn new_file_listener(&mut self, path: &Path) -> broadcast::Receiver<()> {
         let (tx, rx) = broadcast::channel(1);
 
@@ -267,4 +335,12 @@ impl Listeners {
     pub fn new_volume_change_listener(&self) -> broadcast::Receiver<()> {
         self.volume_listener.as_ref().unwrap().subscribe()
     }
+
+    pub fn new_media_listener(&self) -> broadcast::Receiver<()> {
+        self.media_listener.as_ref().unwrap().subscribe()
+    }
+
+    pub fn new_tray_listener(&self) -> broadcast::Receiver<()> {
+        self.tray_listener.as_ref().unwrap().subscribe()
+    }
 }
@@ -1,2 +1,12 @@
+pub mod alerts;
+pub mod audio;
+pub mod compositor;
+pub mod dbus;
 pub mod helpers;
+pub mod icons;
 pub mod listeners;
+pub mod mpris;
+pub mod pipewire;
+pub mod pulse;
+pub mod toplevel;
+pub mod tray;
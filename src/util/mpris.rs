@@ -0,0 +1,305 @@
+use super::dbus::{self, with_session};
+use lazy_static::lazy_static;
+use log::warn;
+use std::{collections::HashMap, sync::Mutex};
+use tokio::sync::broadcast;
+use zbus::{
+    blocking::{
+        fdo::{DBusProxy, PropertiesProxy},
+        Connection,
+    },
+    message::Type,
+    names::{BusName, InterfaceName},
+    zvariant::{OwnedValue, Value},
+    MatchRule,
+};
+
+const PREFIX: &str = "org.mpris.MediaPlayer2.";
+const PATH: &str = "/org/mpris/MediaPlayer2";
+const INTERFACE: &str = "org.mpris.MediaPlayer2.Player";
+
+lazy_static! {
+    // Most recently active player first
+    static ref PLAYERS: Mutex<Vec<Player>> = Mutex::new(Vec::new());
+}
+
+#[derive(Clone, Copy, PartialEq, Default)]
+pub enum Status {
+    Playing,
+    Paused,
+    #[default]
+    Stopped,
+}
+
+impl Status {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Status::Playing => "playing",
+            Status::Paused => "paused",
+            Status::Stopped => "stopped",
+        }
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct Player {
+    pub name: Box<str>,
+    // Unique name signals are sent from
+    owner: Box<str>,
+    pub status: Status,
+    pub artist: Box<str>,
+    pub title: Box<str>,
+    pub album: Box<str>,
+}
+
+impl Player {
+    fn update(&mut self, mut properties: HashMap<String, OwnedValue>) {
+        if let Some(status) = properties
+            .remove("PlaybackStatus")
+            .and_then(|status| String::try_from(status).ok())
+        {
+            self.status = match status.as_str() {
+                "Playing" => Status::Playing,
+                "Paused" => Status::Paused,
+                _ => Status::Stopped,
+            };
+        }
+
+        if let Some(metadata) = properties.remove("Metadata").and_then(|metadata| {
+            HashMap::<String, OwnedValue>::try_from(Value::from(metadata)).ok()
+        }) {
+            let string = |key: &str| -> Box<str> {
+                metadata
+                    .get(key)
+                    .and_then(|value| <&str>::try_from(&**value).ok())
+                    .unwrap_or_default()
+                    .into()
+            };
+
+            self.title = string("xesam:title");
+            self.album = string("xesam:album");
+            // Artists are a list of strings
+            self.artist = match metadata.get("xesam:artist").map(|value| &**value) {
+                Some(Value::Array(artists)) => artists
+                    .iter()
+                    .filter_map(|artist| <&str>::try_from(artist).ok())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+                    .into(),
+                _ => "".into(),
+            };
+        }
+    }
+}
+
+/// Player that was active most recently
+pub fn current_player() -> Option<Player> {
+    PLAYERS.lock().unwrap().first().cloned()
+}
+
+/// Calls a method like "PlayPause" or "Next" on the current player
+pub fn call(method: &str) -> anyhow::Result<()> {
+    let player = current_player().ok_or_else(|| anyhow::anyhow!("No media player found"))?;
+
+    with_session(|session| {
+        session.call_method(Some(&*player.name), PATH, Some(INTERFACE), method, &())?;
+        Ok(())
+    })
+}
+
+fn get_player(connection: &Connection, name: &str, owner: &str) -> anyhow::Result<Player> {
+    let properties = PropertiesProxy::builder(connection)
+        .destination(name)?
+        .path(PATH)?
+        .build()?
+        .get_all(InterfaceName::from_static_str(INTERFACE)?)?;
+
+    let mut player = Player {
+        name: name.into(),
+        owner: owner.into(),
+        ..Default::default()
+    };
+    player.update(properties);
+
+    Ok(player)
+}
+
+/// Replaces known players with the ones currently on the bus, playing ones first
+fn load_players(connection: &Connection) -> anyhow::Result<()> {
+    let dbus = DBusProxy::new(connection)?;
+    let mut players = dbus
+        .list_names()?
+        .into_iter()
+        .filter(|name| name.as_str().starts_with(PREFIX))
+        .filter_map(|name| {
+            let name = name.as_str();
+            let owner = dbus.get_name_owner(BusName::try_from(name).ok()?).ok()?;
+            get_player(connection, name, owner.as_str())
+                .map_err(|e| warn!("Failed to get media player {name}\n {}", e))
+                .ok()
+        })
+        .collect::<Vec<_>>();
+    players.sort_by_key(|player| player.status != Status::Playing);
+
+    *PLAYERS.lock().unwrap() = players;
+    Ok(())
+}
+
+fn handle_message(connection: &Connection, message: &zbus::Message) -> anyhow::Result<()> {
+    let header = message.header();
+
+    match header.member().map(|member| member.as_str()) {
+        Some("PropertiesChanged") => {
+            let sender = header
+                .sender()
+                .ok_or_else(|| anyhow::anyhow!("Signal without sender"))?;
+            let (_, properties, _) =
+                message
+                    .body()
+                    .deserialize::<(String, HashMap<String, OwnedValue>, Vec<String>)>()?;
+
+            let mut players = PLAYERS.lock().unwrap();
+            let Some(index) = players
+                .iter()
+                .position(|player| *player.owner == *sender.as_str())
+            else {
+                return Ok(());
+            };
+
+            players[index].update(properties);
+            if players[index].status == Status::Playing {
+                let player = players.remove(index);
+                players.insert(0, player);
+            }
+        }
+        Some("NameOwnerChanged") => {
+            let (name, _, new_owner) = message.body().deserialize::<(String, String, String)>()?;
+            if !name.starts_with(PREFIX) {
+                return Ok(());
+            }
+
+            PLAYERS
+                .lock()
+                .unwrap()
+                .retain(|player| *player.name != *name);
+
+            if !new_owner.is_empty() {
+                let player = get_player(connection, &name, &new_owner)?;
+                PLAYERS.lock().unwrap().insert(0, player);
+            }
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
+
+/// Runs until the connection is lost or nobody is listening anymore
+fn run(tx: &broadcast::Sender<()>) -> anyhow::Result<()> {
+    // Session bus address is taken from DBUS_SESSION_BUS_ADDRESS so a private bus can be used
+    let connection = Connection::session()?;
+
+    let properties_rule = MatchRule::builder()
+        .msg_type(Type::Signal)
+        .interface("org.freedesktop.DBus.Properties")?
+        .member("PropertiesChanged")?
+        .path(PATH)?
+        .build();
+    let owner_rule = MatchRule::builder()
+        .msg_type(Type::Signal)
+        .sender("org.freedesktop.DBus")?
+        .interface("org.freedesktop.DBus")?
+        .member("NameOwnerChanged")?
+        .build();
+
+    let message_rx = dbus::messages(&connection, [properties_rule, owner_rule])?;
+
+    load_players(&connection)?;
+    _ = tx.send(());
+
+    for message in message_rx {
+        if let Err(e) = handle_message(&connection, &message?) {
+            warn!("Failed to handle media player event\n {}", e);
+        }
+
+        if tx.send(()).is_err() {
+            return Ok(());
+        }
+    }
+
+    Err(anyhow::anyhow!("Session bus connection closed"))
+}
+
+pub fn listen(tx: broadcast::Sender<()>) {
+    dbus::listen("Media player listener", tx, run);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::dbus::peer_to_peer;
+    use zbus::{interface, Message};
+
+    const NAME: &str = "org.mpris.MediaPlayer2.fake";
+    const OWNER: &str = ":1.5";
+
+    struct FakePlayer;
+
+    #[interface(name = "org.mpris.MediaPlayer2.Player")]
+    impl FakePlayer {
+        #[zbus(property)]
+        fn playback_status(&self) -> String {
+            "Playing".to_string()
+        }
+
+        #[zbus(property)]
+        fn metadata(&self) -> HashMap<String, OwnedValue> {
+            let value = |value: Value| OwnedValue::try_from(value).unwrap();
+            HashMap::from([
+                ("xesam:title".to_string(), value(Value::from("Song"))),
+                ("xesam:album".to_string(), value(Value::from("Album"))),
+                (
+                    "xesam:artist".to_string(),
+                    value(Value::from(vec!["First", "Second"])),
+                ),
+            ])
+        }
+    }
+
+    // Players are global, so everything happens in one test
+    #[test]
+    fn fake_player() {
+        let (client, _server) = peer_to_peer(PATH, FakePlayer);
+
+        let player = get_player(&client, NAME, OWNER).unwrap();
+        assert!(player.status == Status::Playing);
+        assert_eq!(&*player.title, "Song");
+        assert_eq!(&*player.album, "Album");
+        assert_eq!(&*player.artist, "First, Second");
+        PLAYERS.lock().unwrap().push(player);
+
+        let paused = Message::signal(PATH, "org.freedesktop.DBus.Properties", "PropertiesChanged")
+            .unwrap()
+            .sender(OWNER)
+            .unwrap()
+            .build(&(
+                INTERFACE,
+                HashMap::from([("PlaybackStatus", Value::from("Paused"))]),
+                Vec::<String>::new(),
+            ))
+            .unwrap();
+        handle_message(&client, &paused).unwrap();
+        assert!(current_player().is_some_and(|player| player.status == Status::Paused));
+
+        let closed = Message::signal(
+            "/org/freedesktop/DBus",
+            "org.freedesktop.DBus",
+            "NameOwnerChanged",
+        )
+        .unwrap()
+        .build(&(NAME, OWNER, ""))
+        .unwrap();
+        handle_message(&client, &closed).unwrap();
+        assert!(current_player().is_none());
+    }
+}
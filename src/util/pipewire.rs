@@ -0,0 +1,611 @@
+use super::{
+    audio::{AudioBackend, Device, DeviceInfo, Sink, SinkInput},
+    pulse::PulseBackend,
+};
+use log::warn;
+use pipewire as pw;
+use pw::{
+    context::Context,
+    core::PW_ID_CORE,
+    device::{Device as PwDevice, DeviceListener},
+    main_loop::MainLoop,
+    metadata::{Metadata, MetadataListener},
+    node::{Node, NodeListener},
+    registry::GlobalObject,
+    spa::{
+        param::ParamType,
+        pod::{deserialize::PodDeserializer, Object, Pod, Value, ValueArray},
+        utils::dict::DictRef,
+    },
+    types::ObjectType,
+};
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    rc::Rc,
+    sync::{mpsc, Arc, Mutex},
+    thread,
+    time::Duration,
+};
+use tokio::sync::broadcast;
+
+const MIN_BACKOFF: Duration = Duration::from_millis(500);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+struct NodeState {
+    device: Device,
+    name: Box<str>,
+    description: Box<str>,
+    volume: u8,
+    muted: bool,
+    // Card and route device index used to find the active route (port) of the node
+    card: Option<u32>,
+    route_device: Option<i32>,
+}
+
+#[derive(Default)]
+struct State {
+    nodes: HashMap<u32, NodeState>,
+    default_sink: Option<Box<str>>,
+    default_source: Option<Box<str>>,
+    routes: HashMap<(u32, i32), Box<str>>,
+    // Application names of recording streams
+    recording: HashMap<u32, Box<str>>,
+    listeners: Vec<broadcast::Sender<()>>,
+}
+
+impl State {
+    fn notify(&mut self) {
+        self.listeners.retain(|tx| tx.send(()).is_ok());
+    }
+}
+
+// Proxies have to be kept alive for their listeners to be called
+enum Proxy {
+    Node(Node, NodeListener),
+    Device(PwDevice, DeviceListener),
+    Metadata(Metadata, MetadataListener),
+}
+
+pub struct PipeWireBackend {
+    state: Arc<Mutex<State>>,
+}
+
+impl PipeWireBackend {
+    pub fn new() -> Self {
+        let state = Arc::new(Mutex::new(State::default()));
+        let (ready_tx, ready_rx) = mpsc::channel();
+
+        {
+            let state = state.clone();
+            thread::spawn(move || {
+                pw::init();
+                let mut backoff = MIN_BACKOFF;
+                loop {
+                    let result = run(&state, &ready_tx);
+                    {
+                        let mut state = state.lock().unwrap();
+                        state.nodes.clear();
+                        state.routes.clear();
+                        state.recording.clear();
+                        state.notify();
+                    }
+
+                    match result {
+                        // Still waits, a mainloop that keeps returning would spin otherwise
+                        Ok(()) => {
+                            warn!(
+                                "Lost connection to PipeWire, reconnecting in {:?}",
+                                MIN_BACKOFF
+                            );
+                            backoff = MIN_BACKOFF;
+                            thread::sleep(MIN_BACKOFF);
+                        }
+                        Err(e) => {
+                            warn!(
+                                "Failed to connect to PipeWire, retrying in {:?}\n {}",
+                                backoff, e
+                            );
+                            thread::sleep(backoff);
+                            backoff = (backoff * 2).min(MAX_BACKOFF);
+                        }
+                    }
+                }
+            });
+        }
+
+        // Modules are rendered right after startup so wait for the initial state
+        _ = ready_rx.recv_timeout(Duration::from_secs(1));
+
+        Self { state }
+    }
+}
+
+impl AudioBackend for PipeWireBackend {
+    fn default_device(&self, device: Device) -> anyhow::Result<DeviceInfo> {
+        let state = self.state.lock().unwrap();
+        let name = match device {
+            Device::Sink => &state.default_sink,
+            Device::Source => &state.default_source,
+        }
+        .as_ref()
+        .ok_or_else(|| anyhow::anyhow!("Default device not set"))?;
+
+        let node = state
+            .nodes
+            .values()
+            .find(|node| node.device == device && node.name == *name)
+            .ok_or_else(|| anyhow::anyhow!("Default device {name} not found"))?;
+
+        let port = node
+            .card
+            .zip(node.route_device)
+            .and_then(|key| state.routes.get(&key).cloned());
+
+        Ok(DeviceInfo {
+            volume: node.volume,
+            muted: node.muted,
+            description: node.description.clone(),
+            port,
+        })
+    }
+
+    fn recording(&self) -> anyhow::Result<Vec<Box<str>>> {
+        Ok(self
+            .state
+            .lock()
+            .unwrap()
+            .recording
+            .values()
+            .cloned()
+            .collect())
+    }
+
+    fn listen(&self, tx: broadcast::Sender<()>) {
+        self.state.lock().unwrap().listeners.push(tx);
+    }
+
+    // Streams are managed through pipewire-pulse, which exposes them the same way
+    fn sink_inputs(&self) -> anyhow::Result<Vec<SinkInput>> {
+        PulseBackend.sink_inputs()
+    }
+
+    fn sinks(&self) -> anyhow::Result<Vec<Sink>> {
+        PulseBackend.sinks()
+    }
+
+    fn set_sink_input_volume(&self, index: u32, volume: u8) -> anyhow::Result<()> {
+        PulseBackend.set_sink_input_volume(index, volume)
+    }
+
+    fn set_sink_input_mute(&self, index: u32, mute: bool) -> anyhow::Result<()> {
+        PulseBackend.set_sink_input_mute(index, mute)
+    }
+
+    fn move_sink_input(&self, index: u32, sink: u32) -> anyhow::Result<()> {
+        PulseBackend.move_sink_input(index, sink)
+    }
+}
+
+fn deserialize(param: &Pod) -> Option<Object> {
+    match PodDeserializer::deserialize_any_from(param.as_bytes()) {
+        Ok((_, Value::Object(object))) => Some(object),
+        _ => None,
+    }
+}
+
+/// Returns volume in percent and mute state from a Props param
+fn parse_props(param: &Pod) -> (Option<u8>, Option<bool>) {
+    let Some(object) = deserialize(param) else {
+        return (None, None);
+    };
+
+    object
+        .properties
+        .into_iter()
+        .fold((None, None), |(volume, muted), property| {
+            match (property.key, property.value) {
+                (key, Value::ValueArray(ValueArray::Float(volumes)))
+                    if key == pw::spa::sys::SPA_PROP_channelVolumes && !volumes.is_empty() =>
+                {
+                    // PipeWire uses cubic volumes while pulse compatible percentages are linear
+                    let average = volumes.iter().sum::<f32>() / volumes.len() as f32;
+                    (Some((average.cbrt() * 100.0).round() as u8), muted)
+                }
+                (key, Value::Bool(mute)) if key == pw::spa::sys::SPA_PROP_mute => {
+                    (volume, Some(mute))
+                }
+                _ => (volume, muted),
+            }
+        })
+}
+
+/// Returns route device index and description from a Route param
+fn parse_route(param: &Pod) -> Option<(i32, Box<str>)> {
+    let object = deserialize(param)?;
+
+    let (device, description) =
+        object
+            .properties
+            .into_iter()
+            .fold((None, None), |(device, description), property| {
+                match (property.key, property.value) {
+                    (key, Value::Int(index)) if key == pw::spa::sys::SPA_PARAM_ROUTE_device => {
+                        (Some(index), description)
+                    }
+                    (key, Value::String(value))
+                        if key == pw::spa::sys::SPA_PARAM_ROUTE_description =>
+                    {
+                        (device, Some(value.into_boxed_str()))
+                    }
+                    _ => (device, description),
+                }
+            });
+
+    Some((device?, description?))
+}
+
+/// Default devices are stored as JSON like {"name":"alsa_output.pci-0000_00_1f.3.analog-stereo"}
+fn parse_default(value: &str) -> Option<Box<str>> {
+    let start = value.find("\"name\"")? + "\"name\"".len();
+    let value = value[start..].trim_start().strip_prefix(':')?.trim_start();
+    let value = value.strip_prefix('"')?;
+    Some(value[..value.find('"')?].into())
+}
+
+fn bind(
+    registry: &pw::registry::Registry,
+    global: &GlobalObject<&DictRef>,
+    state: &Arc<Mutex<State>>,
+) -> Option<Proxy> {
+    let props = global.props?;
+
+    match global.type_ {
+        ObjectType::Node if props.get("media.class") == Some("Stream/Input/Audio") => {
+            let name = props
+                .get("application.name")
+                .or_else(|| props.get("node.name"))
+                .unwrap_or_default();
+
+            let mut state = state.lock().unwrap();
+            state.recording.insert(global.id, name.into());
+            state.notify();
+
+            None
+        }
+        ObjectType::Node => {
+            let device = match props.get("media.class")? {
+                "Audio/Sink" => Device::Sink,
+                "Audio/Source" => Device::Source,
+                _ => return None,
+            };
+            let name = props.get("node.name")?;
+
+            state.lock().unwrap().nodes.insert(
+                global.id,
+                NodeState {
+                    device,
+                    name: name.into(),
+                    description: props.get("node.description").unwrap_or(name).into(),
+                    volume: 0,
+                    muted: false,
+                    card: props.get("device.id").and_then(|id| id.parse().ok()),
+                    route_device: props
+                        .get("card.profile.device")
+                        .and_then(|index| index.parse().ok()),
+                },
+            );
+
+            let node = registry.bind::<Node, _>(global).ok()?;
+            let listener = node
+                .add_listener_local()
+                .param({
+                    let state = state.clone();
+                    let id = global.id;
+                    move |_, _, _, _, param| {
+                        let Some(param) = param else {
+                            return;
+                        };
+                        let (volume, muted) = parse_props(param);
+
+                        let mut state = state.lock().unwrap();
+                        if let Some(node) = state.nodes.get_mut(&id) {
+                            node.volume = volume.unwrap_or(node.volume);
+                            node.muted = muted.unwrap_or(node.muted);
+                            state.notify();
+                        }
+                    }
+                })
+                .register();
+            node.subscribe_params(&[ParamType::Props]);
+
+            Some(Proxy::Node(node, listener))
+        }
+        ObjectType::Device if props.get("media.class") == Some("Audio/Device") => {
+            let device = registry.bind::<PwDevice, _>(global).ok()?;
+            let listener = device
+                .add_listener_local()
+                .param({
+                    let state = state.clone();
+                    let id = global.id;
+                    move |_, _, _, _, param| {
+                        let Some((route_device, description)) = param.and_then(parse_route) else {
+                            return;
+                        };
+
+                        let mut state = state.lock().unwrap();
+                        state.routes.insert((id, route_device), description);
+                        state.notify();
+                    }
+                })
+                .register();
+            device.subscribe_params(&[ParamType::Route]);
+
+            Some(Proxy::Device(device, listener))
+        }
+        ObjectType::Metadata if props.get("metadata.name") == Some("default") => {
+            let metadata = registry.bind::<Metadata, _>(global).ok()?;
+            let listener = metadata
+                .add_listener_local()
+                .property({
+                    let state = state.clone();
+                    move |_, key, _, value| {
+                        let value = value.and_then(parse_default);
+
+                        let mut state = state.lock().unwrap();
+                        match key {
+                            Some("default.audio.sink") => state.default_sink = value,
+                            Some("default.audio.source") => state.default_source = value,
+                            _ => return 0,
+                        }
+                        state.notify();
+
+                        0
+                    }
+                })
+                .register();
+
+            Some(Proxy::Metadata(metadata, listener))
+        }
+        _ => None,
+    }
+}
+
+/// Runs until the connection is lost
+fn run(state: &Arc<Mutex<State>>, ready: &mpsc::Sender<()>) -> anyhow::Result<()> {
+    let mainloop = MainLoop::new(None)?;
+    let context = Context::new(&mainloop)?;
+    let core = context.connect(None)?;
+    let registry = Rc::new(core.get_registry()?);
+    let proxies: Rc<RefCell<HashMap<u32, Proxy>>> = Rc::new(RefCell::new(HashMap::new()));
+
+    let _registry_listener = registry
+        .add_listener_local()
+        .global({
+            let registry = registry.clone();
+            let proxies = proxies.clone();
+            let state = state.clone();
+            move |global| {
+                if let Some(proxy) = bind(&registry, global, &state) {
+                    proxies.borrow_mut().insert(global.id, proxy);
+                }
+            }
+        })
+        .global_remove({
+            let proxies = proxies.clone();
+            let state = state.clone();
+            move |id| {
+                proxies.borrow_mut().remove(&id);
+
+                let mut state = state.lock().unwrap();
+                state.routes.retain(|(card, _), _| *card != id);
+                if state.nodes.remove(&id).is_some() || state.recording.remove(&id).is_some() {
+                    state.notify();
+                }
+            }
+        })
+        .register();
+
+    let pending = core.sync(0)?;
+    let _core_listener = core
+        .add_listener_local()
+        .done({
+            let ready = ready.clone();
+            move |id, seq| {
+                if id == PW_ID_CORE && seq.seq() == pending.seq() {
+                    _ = ready.send(());
+                }
+            }
+        })
+        .error({
+            let mainloop = mainloop.downgrade();
+            move |id, _, res, message| {
+                if id == PW_ID_CORE && res == -libc::EPIPE {
+                    warn!("PipeWire connection error: {}", message);
+                    if let Some(mainloop) = mainloop.upgrade() {
+                        mainloop.quit();
+                    }
+                }
+            }
+        })
+        .register();
+
+    mainloop.run();
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pw::spa::pod::{serialize::PodSerializer, Property};
+    use std::io::Cursor;
+
+    fn object(type_: u32, id: u32, properties: Vec<Property>) -> Vec<u8> {
+        let object = Value::Object(Object {
+            type_,
+            id,
+            properties,
+        });
+        PodSerializer::serialize(Cursor::new(Vec::new()), &object)
+            .unwrap()
+            .0
+            .into_inner()
+    }
+
+    #[test]
+    fn props() {
+        let bytes = object(
+            pw::spa::sys::SPA_TYPE_OBJECT_Props,
+            pw::spa::sys::SPA_PARAM_Props,
+            vec![
+                Property::new(
+                    pw::spa::sys::SPA_PROP_channelVolumes,
+                    Value::ValueArray(ValueArray::Float(vec![0.125, 0.125])),
+                ),
+                Property::new(pw::spa::sys::SPA_PROP_mute, Value::Bool(true)),
+            ],
+        );
+
+        assert_eq!(
+            parse_props(Pod::from_bytes(&bytes).unwrap()),
+            (Some(50), Some(true))
+        );
+    }
+
+    #[test]
+    fn route() {
+        let bytes = object(
+            pw::spa::sys::SPA_TYPE_OBJECT_ParamRoute,
+            pw::spa::sys::SPA_PARAM_Route,
+            vec![
+                Property::new(pw::spa::sys::SPA_PARAM_ROUTE_device, Value::Int(1)),
+                Property::new(
+                    pw::spa::sys::SPA_PARAM_ROUTE_description,
+                    Value::String("Headphones".into()),
+                ),
+            ],
+        );
+
+        assert_eq!(
+            parse_route(Pod::from_bytes(&bytes).unwrap()),
+            Some((1, "Headphones".into()))
+        );
+    }
+
+    #[test]
+    fn default_device_name() {
+        assert_eq!(
+            parse_default(r#"{ "name": "alsa_output.pci-0000_00_1f.3.analog-stereo" }"#).as_deref(),
+            Some("alsa_output.pci-0000_00_1f.3.analog-stereo")
+        );
+        assert_eq!(parse_default("{}"), None);
+    }
+
+    #[test]
+    fn default_device() {
+        let mut state = State {
+            default_sink: Some("speakers".into()),
+            ..Default::default()
+        };
+        state.nodes.insert(
+            40,
+            NodeState {
+                device: Device::Sink,
+                name: "speakers".into(),
+                description: "Built-in Audio".into(),
+                volume: 35,
+                muted: false,
+                card: Some(30),
+                route_device: Some(1),
+            },
+        );
+        state.routes.insert((30, 1), "Speakers".into());
+        let backend = PipeWireBackend {
+            state: Arc::new(Mutex::new(state)),
+        };
+
+        let info = backend.default_device(Device::Sink).unwrap();
+        assert_eq!(info.volume, 35);
+        assert_eq!(&*info.description, "Built-in Audio");
+        assert_eq!(info.port.as_deref(), Some("Speakers"));
+        assert!(backend.default_device(Device::Source).is_err());
+    }
+
+    // Daemon with null sinks, a recording stream and default devices set without a session manager
+    const HEADLESS_CONFIG: &str = r#"
+context.properties = { core.daemon = true core.name = pipewire-0 }
+context.spa-libs = { audio.convert.* = audioconvert/libspa-audioconvert support.* = support/libspa-support }
+context.modules = [
+    { name = libpipewire-module-protocol-native }
+    { name = libpipewire-module-client-node }
+    { name = libpipewire-module-adapter }
+    { name = libpipewire-module-metadata }
+]
+context.objects = [
+    { factory = adapter args = { factory.name = support.null-audio-sink node.name = test-sink node.description = "Test Sink" media.class = Audio/Sink audio.position = [ FL FR ] } }
+    { factory = adapter args = { factory.name = support.null-audio-sink node.name = test-source node.description = "Test Source" media.class = Audio/Source audio.position = [ MONO ] } }
+    { factory = adapter args = { factory.name = support.null-audio-sink node.name = test-recorder application.name = Recorder media.class = Stream/Input/Audio audio.position = [ MONO ] } }
+    { factory = metadata args = { metadata.name = default metadata.values = [
+        { key = default.audio.sink value = { name = test-sink } }
+        { key = default.audio.source value = { name = test-source } }
+    ] } }
+]
+"#;
+
+    struct Daemon(std::process::Child);
+
+    impl Drop for Daemon {
+        fn drop(&mut self) {
+            _ = self.0.kill();
+            _ = self.0.wait();
+        }
+    }
+
+    fn eventually(check: impl Fn() -> bool) -> bool {
+        (0..50).any(|_| {
+            let done = check();
+            if !done {
+                thread::sleep(Duration::from_millis(100));
+            }
+            done
+        })
+    }
+
+    /// Needs the `pipewire` binary, run with `cargo test -- --ignored`
+    #[test]
+    #[ignore]
+    fn headless_daemon() {
+        let dir = std::env::temp_dir().join(format!("pipewire-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let config = dir.join("pipewire.conf");
+        std::fs::write(&config, HEADLESS_CONFIG).unwrap();
+
+        let _daemon = Daemon(
+            std::process::Command::new("pipewire")
+                .arg("-c")
+                .arg(&config)
+                .env("PIPEWIRE_RUNTIME_DIR", &dir)
+                .spawn()
+                .expect("pipewire is not installed"),
+        );
+        assert!(eventually(|| dir.join("pipewire-0").exists()));
+
+        // Clients find the daemon through the same variable
+        std::env::set_var("PIPEWIRE_RUNTIME_DIR", &dir);
+        let backend = PipeWireBackend::new();
+        assert!(eventually(|| backend
+            .default_device(Device::Source)
+            .is_ok()
+            && !backend.recording().unwrap().is_empty()));
+
+        let sink = backend.default_device(Device::Sink).unwrap();
+        assert_eq!(&*sink.description, "Test Sink");
+        assert!(!sink.muted);
+        let source = backend.default_device(Device::Source).unwrap();
+        assert_eq!(&*source.description, "Test Source");
+        assert_eq!(backend.recording().unwrap(), [Box::from("Recorder")]);
+
+        _ = std::fs::remove_dir_all(dir);
+    }
+}
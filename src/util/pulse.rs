@@ -0,0 +1,588 @@
+use super::audio::{AudioBackend, Device, DeviceInfo, Sink, SinkInput};
+use lazy_static::lazy_static;
+use libpulse_binding as pulse;
+use log::warn;
+use pulse::{
+    callbacks::ListResult,
+    context::{
+        subscribe::{Facility, InterestMaskSet, Operation as SubscribeOperation},
+        Context, FlagSet, State as ContextState,
+    },
+    mainloop::threaded::Mainloop,
+    proplist::Proplist,
+    volume::{ChannelVolumes, Volume},
+};
+use std::{
+    sync::{mpsc, Mutex},
+    thread,
+    time::Duration,
+};
+use tokio::sync::broadcast;
+
+type Job = Box<dyn FnOnce(&mut Option<Pulse>) + Send>;
+
+lazy_static! {
+    // Connection can't leave the thread it was created on, other threads hand it jobs instead
+    static ref JOBS: Mutex<mpsc::Sender<Job>> = Mutex::new(spawn());
+}
+
+// Operations whose callback never fires, e.g. because the server hangs, are given up on
+const OPERATION_TIMEOUT: Duration = Duration::from_secs(5);
+
+fn spawn() -> mpsc::Sender<Job> {
+    let (tx, rx) = mpsc::channel::<Job>();
+    thread::spawn(move || {
+        // One connection shared by all audio modules and the volume listener
+        let mut pulse = None;
+        rx.into_iter().for_each(|job| job(&mut pulse));
+    });
+    tx
+}
+
+fn volume_percent(volume: &ChannelVolumes) -> u8 {
+    ((volume.avg().0 as f64 / Volume::NORMAL.0 as f64) * 100.0).round() as u8
+}
+
+/// Scales every channel by the same factor so the average becomes `volume` percent
+fn scale_channels(channels: &mut ChannelVolumes, volume: u8) {
+    let target = Volume::NORMAL.0 as f64 * volume.min(100) as f64 / 100.0;
+    let average = channels.avg().0 as f64;
+    for channel in channels.get_mut() {
+        channel.0 = match average > 0.0 {
+            true => (channel.0 as f64 * target / average).round(),
+            // Silent streams have no balance left to keep
+            false => target,
+        }
+        .min(Volume::MAX.0 as f64) as u32;
+    }
+}
+
+/// Callbacks run on the mainloop thread and only get channels to report back through
+pub struct Pulse {
+    // Dropped before the mainloop it was created with
+    context: Context,
+    mainloop: Mainloop,
+}
+
+impl Drop for Pulse {
+    fn drop(&mut self) {
+        self.mainloop.lock();
+        self.context.disconnect();
+        self.mainloop.unlock();
+        self.mainloop.stop();
+    }
+}
+
+impl Pulse {
+    fn new() -> anyhow::Result<Self> {
+        let mut proplist = Proplist::new().ok_or_else(|| anyhow::anyhow!(""))?;
+        proplist
+            .set_str(
+                pulse::proplist::properties::APPLICATION_NAME,
+                env!("CARGO_PKG_NAME"),
+            )
+            .map_err(|_| anyhow::anyhow!(""))?;
+
+        let mainloop = Mainloop::new().ok_or_else(|| anyhow::anyhow!(""))?;
+        let context = Context::new_with_proplist(&mainloop, "MainConn", &proplist)
+            .ok_or_else(|| anyhow::anyhow!(""))?;
+        let mut pulse = Self { context, mainloop };
+
+        let (state_tx, state_rx) = mpsc::channel();
+        pulse
+            .context
+            .set_state_callback(Some(Box::new(move || _ = state_tx.send(()))));
+        pulse.context.connect(None, FlagSet::NOFLAGS, None)?;
+
+        pulse.mainloop.lock();
+        if let Err(e) = pulse.mainloop.start() {
+            pulse.mainloop.unlock();
+            return Err(e.into());
+        }
+
+        let result = loop {
+            match pulse.context.get_state() {
+                ContextState::Ready => break Ok(()),
+                ContextState::Failed | ContextState::Terminated => {
+                    break Err(anyhow::anyhow!(
+                        "Context state failed/terminated without an error"
+                    ))
+                }
+                _ => {
+                    if let Err(e) = pulse.wait(&state_rx) {
+                        break Err(e);
+                    }
+                }
+            }
+        };
+
+        pulse.context.set_state_callback(None);
+        pulse.mainloop.unlock();
+
+        result.map(|_| pulse)
+    }
+
+    fn is_ready(&mut self) -> bool {
+        self.mainloop.lock();
+        let state = self.context.get_state();
+        self.mainloop.unlock();
+        state == ContextState::Ready
+    }
+
+    /// Waits for a callback to report back, mainloop has to be locked
+    fn wait<T>(&mut self, rx: &mpsc::Receiver<T>) -> anyhow::Result<T> {
+        self.mainloop.unlock();
+        let result = rx.recv_timeout(OPERATION_TIMEOUT);
+        self.mainloop.lock();
+        result.map_err(|_| anyhow::anyhow!("Operation cancelled without an error"))
+    }
+
+    fn locked<T>(&mut self, f: impl FnOnce(&mut Self) -> anyhow::Result<T>) -> anyhow::Result<T> {
+        self.mainloop.lock();
+        let result = f(self);
+        self.mainloop.unlock();
+        result
+    }
+
+    fn default_device_name(&mut self, device: Device) -> anyhow::Result<Box<str>> {
+        let (tx, rx) = mpsc::channel();
+        let _op = self.context.introspect().get_server_info(move |result| {
+            let default_name = match device {
+                Device::Sink => &result.default_sink_name,
+                Device::Source => &result.default_source_name,
+            };
+            _ = tx.send(
+                default_name
+                    .as_ref()
+                    .map(|cow| Box::<str>::from(cow.as_ref())),
+            );
+        });
+
+        self.wait(&rx)?
+            .ok_or_else(|| anyhow::anyhow!("Default device not set"))
+    }
+
+    pub fn default_device(&mut self, device: Device) -> anyhow::Result<DeviceInfo> {
+        self.locked(|pulse| {
+            let name = pulse.default_device_name(device)?;
+            let introspect = pulse.context.introspect();
+
+            let info = match device {
+                Device::Sink => {
+                    let (tx, rx) = mpsc::channel();
+                    let mut info = None;
+                    let _op = introspect.get_sink_info_by_name(&name, move |result| match result {
+                        ListResult::Item(item) => {
+                            info.replace(DeviceInfo {
+                                volume: volume_percent(&item.volume),
+                                muted: item.mute,
+                                description: item.description.as_deref().unwrap_or_default().into(),
+                                port: item
+                                    .active_port
+                                    .as_ref()
+                                    .and_then(|port| port.description.as_deref().map(Into::into)),
+                            });
+                        }
+                        ListResult::End | ListResult::Error => _ = tx.send(info.take()),
+                    });
+                    pulse.wait(&rx)?
+                }
+                Device::Source => {
+                    let (tx, rx) = mpsc::channel();
+                    let mut info = None;
+                    let _op =
+                        introspect.get_source_info_by_name(&name, move |result| match result {
+                            ListResult::Item(item) => {
+                                info.replace(DeviceInfo {
+                                    volume: volume_percent(&item.volume),
+                                    muted: item.mute,
+                                    description: item
+                                        .description
+                                        .as_deref()
+                                        .unwrap_or_default()
+                                        .into(),
+                                    port: item.active_port.as_ref().and_then(|port| {
+                                        port.description.as_deref().map(Into::into)
+                                    }),
+                                });
+                            }
+                            ListResult::End | ListResult::Error => _ = tx.send(info.take()),
+                        });
+                    pulse.wait(&rx)?
+                }
+            };
+
+            info.ok_or_else(|| anyhow::anyhow!("Default device {name} not found"))
+        })
+    }
+
+    pub fn sink_inputs(&mut self) -> anyhow::Result<Vec<SinkInput>> {
+        self.locked(|pulse| {
+            let (tx, rx) = mpsc::channel();
+            let mut inputs = Vec::new();
+            let _op =
+                pulse
+                    .context
+                    .introspect()
+                    .get_sink_input_info_list(move |result| match result {
+                        ListResult::Item(item) => {
+                            let proplist_name = item
+                                .proplist
+                                .get_str(pulse::proplist::properties::APPLICATION_NAME);
+                            inputs.push(SinkInput {
+                                index: item.index,
+                                name: proplist_name
+                                    .or_else(|| item.name.as_deref().map(Into::into))
+                                    .unwrap_or_default()
+                                    .into(),
+                                icon: item
+                                    .proplist
+                                    .get_str(pulse::proplist::properties::APPLICATION_ICON_NAME)
+                                    .map(Into::into),
+                                volume: volume_percent(&item.volume),
+                                muted: item.mute,
+                                sink: item.sink,
+                            });
+                        }
+                        ListResult::End | ListResult::Error => {
+                            _ = tx.send(std::mem::take(&mut inputs))
+                        }
+                    });
+
+            pulse.wait(&rx)
+        })
+    }
+
+    /// Names of applications recording from any source
+    pub fn source_outputs(&mut self) -> anyhow::Result<Vec<Box<str>>> {
+        self.locked(|pulse| {
+            let (tx, rx) = mpsc::channel();
+            let mut names = Vec::new();
+            let _op = pulse
+                .context
+                .introspect()
+                .get_source_output_info_list(move |result| match result {
+                    ListResult::Item(item) => {
+                        let name = item
+                            .proplist
+                            .get_str(pulse::proplist::properties::APPLICATION_NAME)
+                            .or_else(|| item.name.as_deref().map(Into::into))
+                            .unwrap_or_default();
+                        names.push(name.into());
+                    }
+                    ListResult::End | ListResult::Error => _ = tx.send(std::mem::take(&mut names)),
+                });
+
+            pulse.wait(&rx)
+        })
+    }
+
+    pub fn sinks(&mut self) -> anyhow::Result<Vec<Sink>> {
+        self.locked(|pulse| {
+            let (tx, rx) = mpsc::channel();
+            let mut sinks = Vec::new();
+            let _op = pulse
+                .context
+                .introspect()
+                .get_sink_info_list(move |result| match result {
+                    ListResult::Item(item) => sinks.push(Sink {
+                        index: item.index,
+                        description: item.description.as_deref().unwrap_or_default().into(),
+                    }),
+                    ListResult::End | ListResult::Error => _ = tx.send(std::mem::take(&mut sinks)),
+                });
+
+            pulse.wait(&rx)
+        })
+    }
+
+    /// Callback for operations which only report success, along with where it reports to
+    fn on_success() -> (Option<Box<dyn FnMut(bool) + 'static>>, mpsc::Receiver<bool>) {
+        let (tx, rx) = mpsc::channel();
+        (Some(Box::new(move |success| _ = tx.send(success))), rx)
+    }
+
+    /// Waits for an operation created with `on_success`
+    fn wait_success(&mut self, rx: &mpsc::Receiver<bool>) -> anyhow::Result<()> {
+        match self.wait(rx)? {
+            true => Ok(()),
+            false => Err(anyhow::anyhow!(
+                "Operation failed\n {}",
+                self.context.errno()
+            )),
+        }
+    }
+
+    /// Volume is capped at 100 percent, amplifying streams is left to other mixers
+    pub fn set_sink_input_volume(&mut self, index: u32, volume: u8) -> anyhow::Result<()> {
+        self.locked(|pulse| {
+            // Current channels rather than ones listed earlier, balance may have changed since
+            let (tx, rx) = mpsc::channel();
+            let _op = pulse
+                .context
+                .introspect()
+                .get_sink_input_info(index, move |result| {
+                    if let ListResult::Item(item) = result {
+                        _ = tx.send(item.volume);
+                    }
+                });
+            let mut channels = pulse
+                .wait(&rx)
+                .map_err(|_| anyhow::anyhow!("Audio stream {index} not found"))?;
+            scale_channels(&mut channels, volume);
+
+            let (callback, rx) = Self::on_success();
+            let _op = pulse
+                .context
+                .introspect()
+                .set_sink_input_volume(index, &channels, callback);
+            pulse.wait_success(&rx)
+        })
+    }
+
+    pub fn set_sink_input_mute(&mut self, index: u32, mute: bool) -> anyhow::Result<()> {
+        self.locked(|pulse| {
+            let (callback, rx) = Self::on_success();
+            let _op = pulse
+                .context
+                .introspect()
+                .set_sink_input_mute(index, mute, callback);
+            pulse.wait_success(&rx)
+        })
+    }
+
+    pub fn move_sink_input(&mut self, index: u32, sink: u32) -> anyhow::Result<()> {
+        self.locked(|pulse| {
+            let (callback, rx) = Self::on_success();
+            let _op = pulse
+                .context
+                .introspect()
+                .move_sink_input_by_index(index, sink, callback);
+            pulse.wait_success(&rx)
+        })
+    }
+
+    /// Replaces the subscription of the connection, `on_state_change` is called whenever the connection state changes
+    pub fn subscribe(
+        &mut self,
+        mask: InterestMaskSet,
+        mut callback: impl FnMut(Facility, SubscribeOperation) + 'static,
+        mut on_state_change: impl FnMut() + 'static,
+    ) -> anyhow::Result<()> {
+        self.locked(|pulse| {
+            pulse
+                .context
+                .set_subscribe_callback(Some(Box::new(move |facility, operation, _| {
+                    if let (Some(facility), Some(operation)) = (facility, operation) {
+                        callback(facility, operation);
+                    }
+                })));
+            pulse
+                .context
+                .set_state_callback(Some(Box::new(move || on_state_change())));
+            pulse.context.subscribe(mask, |_| {});
+            Ok(())
+        })
+    }
+
+    pub fn unsubscribe(&mut self) {
+        _ = self.locked(|pulse| {
+            pulse.context.subscribe(InterestMaskSet::NULL, |_| {});
+            pulse.context.set_subscribe_callback(None);
+            pulse.context.set_state_callback(None);
+            Ok(())
+        });
+    }
+}
+
+/// Runs `job` on the thread owning the connection and waits for it to finish
+fn run<T: Send + 'static>(
+    job: impl FnOnce(&mut Option<Pulse>) -> T + Send + 'static,
+) -> anyhow::Result<T> {
+    let (tx, rx) = mpsc::channel();
+    JOBS.lock()
+        .unwrap()
+        .send(Box::new(move |pulse| _ = tx.send(job(pulse))))
+        .map_err(|_| anyhow::anyhow!("Pulseaudio thread is gone"))?;
+    Ok(rx.recv()?)
+}
+
+/// Runs `f` with the shared connection, connecting first if there is none or it was lost
+pub fn with_pulse<T: Send + 'static>(
+    f: impl FnOnce(&mut Pulse) -> anyhow::Result<T> + Send + 'static,
+) -> anyhow::Result<T> {
+    run(|pulse| {
+        if !pulse.as_mut().is_some_and(|pulse| pulse.is_ready()) {
+            pulse.take();
+            pulse.replace(Pulse::new()?);
+        }
+
+        f(pulse.as_mut().unwrap())
+    })?
+}
+
+/// Checks the shared connection without reconnecting
+fn connection_lost() -> bool {
+    run(|pulse| pulse.as_mut().map_or(true, |pulse| !pulse.is_ready())).unwrap_or(true)
+}
+
+enum PulseEvent {
+    StateChanged,
+    Shutdown,
+}
+
+const PULSE_MIN_BACKOFF: Duration = Duration::from_millis(500);
+const PULSE_MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+fn is_relevant(facility: Facility, operation: SubscribeOperation) -> bool {
+    match facility {
+        // Default sink or source was switched
+        Facility::Server => operation == SubscribeOperation::Changed,
+        Facility::Sink | Facility::Source => operation != SubscribeOperation::New,
+        // Port or profile changed, e.g. headphones plugged in
+        Facility::Card => operation == SubscribeOperation::Changed,
+        // Application streams shown in the mixer and privacy module
+        Facility::SinkInput | Facility::SourceOutput => true,
+        _ => false,
+    }
+}
+
+/// Subscribes to the shared connection and resubscribes with backoff whenever it is lost,
+/// returns once nobody is listening anymore
+fn listen(tx: broadcast::Sender<()>) {
+    let mut backoff = PULSE_MIN_BACKOFF;
+
+    loop {
+        let (event_tx, event_rx) = mpsc::channel();
+        let subscribed = with_pulse({
+            let tx = tx.clone();
+            let shutdown_tx = event_tx.clone();
+            move |pulse| {
+                pulse.subscribe(
+                    InterestMaskSet::SERVER
+                        | InterestMaskSet::SINK
+                        | InterestMaskSet::SOURCE
+                        | InterestMaskSet::CARD
+                        | InterestMaskSet::SINK_INPUT
+                        | InterestMaskSet::SOURCE_OUTPUT,
+                    move |facility, operation| {
+                        if is_relevant(facility, operation) && tx.send(()).is_err() {
+                            _ = shutdown_tx.send(PulseEvent::Shutdown);
+                        }
+                    },
+                    move || {
+                        _ = event_tx.send(PulseEvent::StateChanged);
+                    },
+                )
+            }
+        });
+
+        match subscribed {
+            Ok(()) => {
+                backoff = PULSE_MIN_BACKOFF;
+                // Server might have been restarted with different defaults
+                _ = tx.send(());
+
+                // Callbacks are dropped along with the connection, so a closed channel means it was replaced
+                loop {
+                    match event_rx.recv() {
+                        Ok(PulseEvent::Shutdown) => {
+                            _ = with_pulse(|pulse| {
+                                pulse.unsubscribe();
+                                Ok(())
+                            });
+                            return;
+                        }
+                        Ok(PulseEvent::StateChanged) if !connection_lost() => {}
+                        Ok(PulseEvent::StateChanged) | Err(_) => {
+                            warn!("Lost connection to pulseaudio, reconnecting");
+                            break;
+                        }
+                    }
+                }
+            }
+            Err(e) => {
+                warn!(
+                    "Failed to connect to pulseaudio, retrying in {:?}\n {}",
+                    backoff, e
+                );
+                thread::sleep(backoff);
+                backoff = (backoff * 2).min(PULSE_MAX_BACKOFF);
+            }
+        }
+
+        if tx.receiver_count() == 0 {
+            return;
+        }
+    }
+}
+
+pub struct PulseBackend;
+
+impl AudioBackend for PulseBackend {
+    fn default_device(&self, device: Device) -> anyhow::Result<DeviceInfo> {
+        with_pulse(move |pulse| pulse.default_device(device))
+    }
+
+    fn recording(&self) -> anyhow::Result<Vec<Box<str>>> {
+        with_pulse(|pulse| pulse.source_outputs())
+    }
+
+    fn listen(&self, tx: broadcast::Sender<()>) {
+        listen(tx)
+    }
+
+    fn sink_inputs(&self) -> anyhow::Result<Vec<SinkInput>> {
+        with_pulse(|pulse| pulse.sink_inputs())
+    }
+
+    fn sinks(&self) -> anyhow::Result<Vec<Sink>> {
+        with_pulse(|pulse| pulse.sinks())
+    }
+
+    fn set_sink_input_volume(&self, index: u32, volume: u8) -> anyhow::Result<()> {
+        with_pulse(move |pulse| pulse.set_sink_input_volume(index, volume))
+    }
+
+    fn set_sink_input_mute(&self, index: u32, mute: bool) -> anyhow::Result<()> {
+        with_pulse(move |pulse| pulse.set_sink_input_mute(index, mute))
+    }
+
+    fn move_sink_input(&self, index: u32, sink: u32) -> anyhow::Result<()> {
+        with_pulse(move |pulse| pulse.move_sink_input(index, sink))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scale_keeps_balance() {
+        let mut channels = ChannelVolumes::default();
+        channels.set_len(2);
+        channels.get_mut()[0] = Volume(Volume::NORMAL.0 / 2);
+        channels.get_mut()[1] = Volume(Volume::NORMAL.0 / 4);
+
+        scale_channels(&mut channels, 75);
+        let [left, right] = channels.get() else {
+            panic!("Channel count changed");
+        };
+        assert_eq!(left.0, Volume::NORMAL.0);
+        assert_eq!(right.0, Volume::NORMAL.0 / 2);
+
+        // The average is what gets capped, louder channels may still go past 100
+        scale_channels(&mut channels, 150);
+        assert_eq!(volume_percent(&channels), 100);
+        assert!(channels.get()[0].0 > Volume::NORMAL.0);
+    }
+
+    #[test]
+    fn scale_silent_stream() {
+        let mut channels = ChannelVolumes::default();
+        channels.set_len(2);
+        scale_channels(&mut channels, 40);
+        assert_eq!(volume_percent(&channels), 40);
+        assert_eq!(channels.get()[0], channels.get()[1]);
+    }
+}
@@ -0,0 +1,173 @@
+use crate::StatusBar;
+use lazy_static::lazy_static;
+use smithay_client_toolkit::output::OutputState;
+use std::sync::Mutex;
+use tokio::sync::broadcast;
+use wayland_client::{
+    event_created_child,
+    protocol::{wl_output, wl_seat},
+    Connection, Dispatch, QueueHandle, WEnum,
+};
+use wayland_protocols_wlr::foreign_toplevel::v1::client::{
+    zwlr_foreign_toplevel_handle_v1::{self, ZwlrForeignToplevelHandleV1},
+    zwlr_foreign_toplevel_manager_v1::{self, ZwlrForeignToplevelManagerV1},
+};
+
+lazy_static! {
+    static ref TOPLEVELS: Mutex<Vec<Toplevel>> = Mutex::new(Vec::new());
+    // Activating a window needs a seat, the first one announced is used
+    static ref SEAT: Mutex<Option<wl_seat::WlSeat>> = Mutex::new(None);
+    static ref TX: broadcast::Sender<()> = broadcast::Sender::new(1);
+}
+
+#[derive(Clone)]
+pub struct Toplevel {
+    pub handle: ZwlrForeignToplevelHandleV1,
+    pub title: Box<str>,
+    pub app_id: Box<str>,
+    // Names of the outputs the window is on
+    pub outputs: Vec<Box<str>>,
+    pub activated: bool,
+    pub minimized: bool,
+    pub maximized: bool,
+}
+
+impl Toplevel {
+    fn new(handle: ZwlrForeignToplevelHandleV1) -> Self {
+        Self {
+            handle,
+            title: "".into(),
+            app_id: "".into(),
+            outputs: Vec::new(),
+            activated: false,
+            minimized: false,
+            maximized: false,
+        }
+    }
+
+    pub fn state(&self) -> Option<&'static str> {
+        match (self.activated, self.minimized, self.maximized) {
+            (true, _, _) => Some("active"),
+            (false, true, _) => Some("minimized"),
+            (false, false, true) => Some("maximized"),
+            _ => None,
+        }
+    }
+
+    pub fn activate(&self) -> anyhow::Result<()> {
+        let seat = SEAT.lock().unwrap();
+        let seat = seat
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("No seat to activate the window with"))?;
+        self.handle.activate(seat);
+        Ok(())
+    }
+}
+
+/// Receiver notified whenever a window appears, disappears or changes
+pub fn subscribe() -> broadcast::Receiver<()> {
+    TX.subscribe()
+}
+
+pub fn set_seat(seat: &wl_seat::WlSeat) {
+    SEAT.lock().unwrap().get_or_insert_with(|| seat.clone());
+}
+
+/// Windows on given output in the order they were opened, all of them when it's `None`
+pub fn toplevels(output: Option<&str>) -> Vec<Toplevel> {
+    TOPLEVELS
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|toplevel| {
+            output.map_or(true, |output| {
+                toplevel.outputs.iter().any(|o| **o == *output)
+            })
+        })
+        .cloned()
+        .collect()
+}
+
+fn output_name(output_state: &OutputState, output: &wl_output::WlOutput) -> Option<Box<str>> {
+    output_state.info(output)?.name.map(Into::into)
+}
+
+impl Dispatch<ZwlrForeignToplevelManagerV1, ()> for StatusBar {
+    fn event(
+        _state: &mut Self,
+        _proxy: &ZwlrForeignToplevelManagerV1,
+        event: zwlr_foreign_toplevel_manager_v1::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        if let zwlr_foreign_toplevel_manager_v1::Event::Toplevel { toplevel } = event {
+            TOPLEVELS.lock().unwrap().push(Toplevel::new(toplevel));
+        }
+    }
+
+    event_created_child!(StatusBar, ZwlrForeignToplevelManagerV1, [
+        zwlr_foreign_toplevel_manager_v1::EVT_TOPLEVEL_OPCODE => (ZwlrForeignToplevelHandleV1, ()),
+    ]);
+}
+
+impl Dispatch<ZwlrForeignToplevelHandleV1, ()> for StatusBar {
+    fn event(
+        state: &mut Self,
+        proxy: &ZwlrForeignToplevelHandleV1,
+        event: zwlr_foreign_toplevel_handle_v1::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        let mut toplevels = TOPLEVELS.lock().unwrap();
+        if let zwlr_foreign_toplevel_handle_v1::Event::Closed = event {
+            toplevels.retain(|toplevel| &toplevel.handle != proxy);
+            proxy.destroy();
+            _ = TX.send(());
+            return;
+        }
+
+        let Some(toplevel) = toplevels
+            .iter_mut()
+            .find(|toplevel| &toplevel.handle == proxy)
+        else {
+            return;
+        };
+
+        match event {
+            zwlr_foreign_toplevel_handle_v1::Event::Title { title } => {
+                toplevel.title = title.into()
+            }
+            zwlr_foreign_toplevel_handle_v1::Event::AppId { app_id } => {
+                toplevel.app_id = app_id.into()
+            }
+            zwlr_foreign_toplevel_handle_v1::Event::OutputEnter { output } => {
+                if let Some(name) = output_name(&state.output_state, &output) {
+                    toplevel.outputs.push(name);
+                }
+            }
+            zwlr_foreign_toplevel_handle_v1::Event::OutputLeave { output } => {
+                if let Some(name) = output_name(&state.output_state, &output) {
+                    toplevel.outputs.retain(|output| *output != name);
+                }
+            }
+            zwlr_foreign_toplevel_handle_v1::Event::State { state } => {
+                let states = state
+                    .chunks_exact(4)
+                    .map(|bytes| u32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+                    .map(WEnum::<zwlr_foreign_toplevel_handle_v1::State>::from)
+                    .collect::<Vec<_>>();
+                let has = |state| states.contains(&WEnum::Value(state));
+                toplevel.activated = has(zwlr_foreign_toplevel_handle_v1::State::Activated);
+                toplevel.minimized = has(zwlr_foreign_toplevel_handle_v1::State::Minimized);
+                toplevel.maximized = has(zwlr_foreign_toplevel_handle_v1::State::Maximized);
+            }
+            // Changes are sent in batches ending with `done`
+            zwlr_foreign_toplevel_handle_v1::Event::Done => {
+                _ = TX.send(());
+            }
+            _ => {}
+        }
+    }
+}
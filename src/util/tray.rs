@@ -0,0 +1,628 @@
+use super::{
+    dbus::{self, with_session},
+    icons::load_icon,
+};
+use image::{imageops::FilterType, DynamicImage, ImageBuffer};
+use lazy_static::lazy_static;
+use log::warn;
+use std::{collections::HashMap, path::Path, sync::Mutex};
+use tokio::sync::broadcast;
+use zbus::{
+    blocking::{
+        fdo::{DBusProxy, PropertiesProxy},
+        Connection,
+    },
+    interface,
+    message::{Header, Type},
+    names::{BusName, InterfaceName},
+    zvariant::{OwnedObjectPath, OwnedValue, Value},
+    MatchRule, SignalContext,
+};
+
+const WATCHER: &str = "org.kde.StatusNotifierWatcher";
+const WATCHER_PATH: &str = "/StatusNotifierWatcher";
+const ITEM_INTERFACE: &str = "org.kde.StatusNotifierItem";
+const ITEM_PATH: &str = "/StatusNotifierItem";
+const MENU_INTERFACE: &str = "com.canonical.dbusmenu";
+
+lazy_static! {
+    static ref ITEMS: Mutex<Vec<Item>> = Mutex::new(Vec::new());
+}
+
+#[derive(Clone)]
+pub struct Item {
+    // Name the item was registered with, either a bus name or bus name followed by object path
+    service: Box<str>,
+    destination: Box<str>,
+    path: Box<str>,
+    owner: Box<str>,
+    passive: bool,
+    icon_name: Option<Box<str>>,
+    icon_theme_path: Option<Box<str>>,
+    // Width, height and ARGB32 data in network byte order
+    icon_pixmaps: Vec<(i32, i32, Vec<u8>)>,
+    menu: Option<Box<str>>,
+    revision: u32,
+}
+
+impl Item {
+    fn new(connection: &Connection, service: &str) -> anyhow::Result<Self> {
+        let destination = service.split('/').next().unwrap_or_default();
+        let owner = DBusProxy::new(connection)?.get_name_owner(BusName::try_from(destination)?)?;
+
+        Self::with_owner(connection, service, owner.as_str())
+    }
+
+    fn with_owner(connection: &Connection, service: &str, owner: &str) -> anyhow::Result<Self> {
+        let (destination, path) = match service.find('/') {
+            Some(index) => (&service[..index], &service[index..]),
+            None => (service, ITEM_PATH),
+        };
+
+        let mut item = Self {
+            service: service.into(),
+            destination: destination.into(),
+            path: path.into(),
+            owner: owner.into(),
+            passive: false,
+            icon_name: None,
+            icon_theme_path: None,
+            icon_pixmaps: Vec::new(),
+            menu: None,
+            revision: 0,
+        };
+        item.refresh(connection)?;
+
+        Ok(item)
+    }
+
+    fn refresh(&mut self, connection: &Connection) -> anyhow::Result<()> {
+        let mut properties = PropertiesProxy::builder(connection)
+            .destination(&*self.destination)?
+            .path(&*self.path)?
+            .build()?
+            .get_all(InterfaceName::from_static_str(ITEM_INTERFACE)?)?;
+
+        let mut string = |key: &str| -> Option<Box<str>> {
+            properties
+                .remove(key)
+                .and_then(|value| String::try_from(value).ok())
+                .filter(|value| !value.is_empty())
+                .map(Into::into)
+        };
+
+        self.passive = string("Status").is_some_and(|status| &*status == "Passive");
+        self.icon_name = string("IconName");
+        self.icon_theme_path = string("IconThemePath");
+        self.icon_pixmaps = properties
+            .remove("IconPixmap")
+            .and_then(|value| Vec::<(i32, i32, Vec<u8>)>::try_from(Value::from(value)).ok())
+            .unwrap_or_default();
+        self.menu = properties
+            .remove("Menu")
+            .and_then(|value| OwnedObjectPath::try_from(value).ok())
+            .map(|path| path.as_str().into());
+        self.revision += 1;
+
+        Ok(())
+    }
+
+    /// Changes whenever the item or its icon changes
+    pub fn key(&self) -> String {
+        format!("{}:{}", self.service, self.revision)
+    }
+
+    /// Destination and object path of the menu of the item
+    pub fn menu(&self) -> Option<(Box<str>, Box<str>)> {
+        Some((self.destination.clone(), self.menu.clone()?))
+    }
+
+    /// Themed icon is preferred over the pixmap which is only a fallback
+    pub fn icon(&self, size: u32) -> Option<DynamicImage> {
+        let themed = self.icon_name.as_deref().and_then(|name| {
+            self.icon_theme_path
+                .as_deref()
+                .map(|theme_path| Path::new(theme_path).join(format!("{name}.png")))
+                .and_then(|path| image::open(path).ok())
+                .map(|icon| icon.resize_exact(size, size, FilterType::Triangle))
+                .or_else(|| load_icon(name, size))
+        });
+
+        themed.or_else(|| {
+            // Largest pixmap scales down best
+            let (width, height, data) = self
+                .icon_pixmaps
+                .iter()
+                .max_by_key(|(width, height, _)| width * height)?;
+            let rgba = data
+                .chunks_exact(4)
+                .flat_map(|argb| [argb[1], argb[2], argb[3], argb[0]])
+                .collect();
+            let icon = ImageBuffer::from_raw(*width as u32, *height as u32, rgba)?;
+            Some(DynamicImage::ImageRgba8(icon).resize_exact(size, size, FilterType::Triangle))
+        })
+    }
+}
+
+pub struct MenuItem {
+    pub id: i32,
+    pub label: Box<str>,
+    pub enabled: bool,
+    pub submenu: bool,
+}
+
+/// Visible items, passive ones are hidden as the specification suggests
+pub fn items() -> Vec<Item> {
+    ITEMS
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|item| !item.passive)
+        .cloned()
+        .collect()
+}
+
+/// Calls a method like "Activate" or "SecondaryActivate" on the item
+pub fn call<B>(item: &Item, method: &str, body: &B) -> anyhow::Result<()>
+where
+    B: serde::Serialize + zbus::zvariant::DynamicType,
+{
+    with_session(|session| {
+        session.call_method(
+            Some(&*item.destination),
+            &*item.path,
+            Some(ITEM_INTERFACE),
+            method,
+            body,
+        )?;
+        Ok(())
+    })
+}
+
+/// Direct children of given menu item, the root has id 0
+pub fn menu_layout(destination: &str, path: &str, parent: i32) -> anyhow::Result<Vec<MenuItem>> {
+    with_session(|session| get_layout(session, destination, path, parent))
+}
+
+fn get_layout(
+    connection: &Connection,
+    destination: &str,
+    path: &str,
+    parent: i32,
+) -> anyhow::Result<Vec<MenuItem>> {
+    // Menus might be populated lazily
+    _ = connection.call_method(
+        Some(destination),
+        path,
+        Some(MENU_INTERFACE),
+        "AboutToShow",
+        &parent,
+    );
+
+    let reply = connection.call_method(
+        Some(destination),
+        path,
+        Some(MENU_INTERFACE),
+        "GetLayout",
+        &(parent, 1i32, Vec::<&str>::new()),
+    )?;
+    let (_, (_, _, children)) = reply
+        .body()
+        .deserialize::<(u32, (i32, HashMap<String, OwnedValue>, Vec<OwnedValue>))>()?;
+
+    Ok(children
+        .into_iter()
+        .filter_map(|child| {
+            let (id, mut properties, _) =
+                <(i32, HashMap<String, OwnedValue>, Vec<OwnedValue>)>::try_from(Value::from(child))
+                    .ok()?;
+
+            let mut get = |key: &str| properties.remove(key);
+            let visible =
+                get("visible").map_or(true, |value| bool::try_from(value).unwrap_or(true));
+            let separator = get("type")
+                .and_then(|value| String::try_from(value).ok())
+                .is_some_and(|kind| kind == "separator");
+            if !visible || separator {
+                return None;
+            }
+
+            Some(MenuItem {
+                id,
+                // Underscores mark mnemonics
+                label: get("label")
+                    .and_then(|value| String::try_from(value).ok())
+                    .unwrap_or_default()
+                    .replace('_', "")
+                    .into(),
+                enabled: get("enabled").map_or(true, |value| bool::try_from(value).unwrap_or(true)),
+                submenu: get("children-display")
+                    .and_then(|value| String::try_from(value).ok())
+                    .is_some_and(|display| display == "submenu"),
+            })
+        })
+        .collect())
+}
+
+pub fn menu_clicked(destination: &str, path: &str, id: i32) -> anyhow::Result<()> {
+    with_session(|session| {
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)?
+            .as_secs() as u32;
+        session.call_method(
+            Some(destination),
+            path,
+            Some(MENU_INTERFACE),
+            "Event",
+            &(id, "clicked", Value::from(""), timestamp),
+        )?;
+        Ok(())
+    })
+}
+
+/// Watcher used when no other tray is running
+#[derive(Default)]
+struct Watcher {
+    items: Vec<String>,
+}
+
+#[interface(name = "org.kde.StatusNotifierWatcher")]
+impl Watcher {
+    async fn register_status_notifier_item(
+        &mut self,
+        service: &str,
+        #[zbus(header)] header: Header<'_>,
+        #[zbus(signal_context)] ctxt: SignalContext<'_>,
+    ) {
+        // Some items register with their object path instead of a bus name
+        let service = match (service.starts_with('/'), header.sender()) {
+            (true, Some(sender)) => format!("{sender}{service}"),
+            _ => service.to_string(),
+        };
+
+        if !self.items.contains(&service) {
+            _ = Self::status_notifier_item_registered(&ctxt, &service).await;
+            self.items.push(service);
+        }
+    }
+
+    async fn register_status_notifier_host(
+        &mut self,
+        _service: &str,
+        #[zbus(signal_context)] ctxt: SignalContext<'_>,
+    ) {
+        _ = Self::status_notifier_host_registered(&ctxt).await;
+    }
+
+    #[zbus(property)]
+    fn registered_status_notifier_items(&self) -> Vec<String> {
+        self.items.clone()
+    }
+
+    #[zbus(property)]
+    fn is_status_notifier_host_registered(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn protocol_version(&self) -> i32 {
+        0
+    }
+
+    #[zbus(signal)]
+    async fn status_notifier_item_registered(
+        ctxt: &SignalContext<'_>,
+        service: &str,
+    ) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    async fn status_notifier_item_unregistered(
+        ctxt: &SignalContext<'_>,
+        service: &str,
+    ) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    async fn status_notifier_host_registered(ctxt: &SignalContext<'_>) -> zbus::Result<()>;
+}
+
+fn belongs_to(service: &str, name: &str) -> bool {
+    service == name
+        || service
+            .strip_prefix(name)
+            .is_some_and(|path| path.starts_with('/'))
+}
+
+/// Removes items of a bus name that is gone from our watcher if we are the one running it
+fn unregister(connection: &Connection, name: &str) -> anyhow::Result<()> {
+    let Ok(watcher) = connection
+        .object_server()
+        .interface::<_, Watcher>(WATCHER_PATH)
+    else {
+        return Ok(());
+    };
+
+    let removed = {
+        let mut watcher = watcher.get_mut();
+        let (removed, items): (Vec<_>, Vec<_>) = watcher
+            .items
+            .drain(..)
+            .partition(|service| belongs_to(service, name));
+        watcher.items = items;
+        removed
+    };
+
+    removed.iter().try_for_each(|service: &String| {
+        zbus::block_on(Watcher::status_notifier_item_unregistered(
+            watcher.signal_context(),
+            service,
+        ))
+    })?;
+
+    Ok(())
+}
+
+fn add_item(connection: &Connection, service: &str) {
+    match Item::new(connection, service) {
+        Ok(item) => {
+            let mut items = ITEMS.lock().unwrap();
+            items.retain(|other| other.service != item.service);
+            items.push(item);
+        }
+        Err(e) => warn!("Failed to add tray item {service}\n {}", e),
+    }
+}
+
+/// Returns false when the watcher changed and everything has to be registered again
+fn handle_message(connection: &Connection, message: &zbus::Message) -> anyhow::Result<bool> {
+    let header = message.header();
+    let interface = header.interface().map(|interface| interface.as_str());
+
+    match (interface, header.member().map(|member| member.as_str())) {
+        (Some(WATCHER), Some("StatusNotifierItemRegistered")) => {
+            let service = message.body().deserialize::<String>()?;
+            add_item(connection, &service);
+        }
+        (Some(WATCHER), Some("StatusNotifierItemUnregistered")) => {
+            let service = message.body().deserialize::<String>()?;
+            ITEMS
+                .lock()
+                .unwrap()
+                .retain(|item| *item.service != *service);
+        }
+        (_, Some("NameOwnerChanged")) => {
+            let (name, old_owner, new_owner) =
+                message.body().deserialize::<(String, String, String)>()?;
+
+            if name == WATCHER {
+                return Ok(false);
+            }
+            if new_owner.is_empty() && !old_owner.is_empty() {
+                ITEMS
+                    .lock()
+                    .unwrap()
+                    .retain(|item| *item.owner != *old_owner);
+                unregister(connection, &name)?;
+            }
+        }
+        // NewIcon, NewStatus and similar signals of items
+        (Some(ITEM_INTERFACE), _) => {
+            let sender = header
+                .sender()
+                .ok_or_else(|| anyhow::anyhow!("Signal without sender"))?;
+            let item = ITEMS
+                .lock()
+                .unwrap()
+                .iter()
+                .find(|item| *item.owner == *sender.as_str())
+                .cloned();
+
+            if let Some(mut item) = item {
+                item.refresh(connection)?;
+                let mut items = ITEMS.lock().unwrap();
+                if let Some(old) = items.iter_mut().find(|old| old.service == item.service) {
+                    *old = item;
+                }
+            }
+        }
+        _ => {}
+    }
+
+    Ok(true)
+}
+
+/// Runs until the connection is lost or nobody is listening anymore
+fn run(tx: &broadcast::Sender<()>) -> anyhow::Result<()> {
+    let connection = Connection::session()?;
+
+    // Another tray might already be running the watcher in which case we only act as a host
+    connection
+        .object_server()
+        .at(WATCHER_PATH, Watcher::default())?;
+    if connection.request_name(WATCHER).is_err() {
+        connection
+            .object_server()
+            .remove::<Watcher, _>(WATCHER_PATH)?;
+    }
+
+    let host = format!("org.kde.StatusNotifierHost-{}", std::process::id());
+    connection.request_name(host.as_str())?;
+
+    let rules = [
+        MatchRule::builder()
+            .msg_type(Type::Signal)
+            .interface(WATCHER)?
+            .build(),
+        MatchRule::builder()
+            .msg_type(Type::Signal)
+            .interface(ITEM_INTERFACE)?
+            .build(),
+        MatchRule::builder()
+            .msg_type(Type::Signal)
+            .sender("org.freedesktop.DBus")?
+            .interface("org.freedesktop.DBus")?
+            .member("NameOwnerChanged")?
+            .build(),
+    ];
+    let message_rx = dbus::messages(&connection, rules)?;
+
+    connection.call_method(
+        Some(WATCHER),
+        WATCHER_PATH,
+        Some(WATCHER),
+        "RegisterStatusNotifierHost",
+        &host.as_str(),
+    )?;
+
+    let services = PropertiesProxy::builder(&connection)
+        .destination(WATCHER)?
+        .path(WATCHER_PATH)?
+        .build()?
+        .get(
+            InterfaceName::from_static_str(WATCHER)?,
+            "RegisteredStatusNotifierItems",
+        )?;
+    ITEMS.lock().unwrap().clear();
+    Vec::<String>::try_from(Value::from(services))?
+        .iter()
+        .for_each(|service| add_item(&connection, service));
+    _ = tx.send(());
+
+    for message in message_rx {
+        match handle_message(&connection, &message?) {
+            Ok(true) => {}
+            Ok(false) => return Err(anyhow::anyhow!("Status notifier watcher changed")),
+            Err(e) => warn!("Failed to handle tray event\n {}", e),
+        }
+
+        if tx.send(()).is_err() {
+            return Ok(());
+        }
+    }
+
+    Err(anyhow::anyhow!("Session bus connection closed"))
+}
+
+pub fn listen(tx: broadcast::Sender<()>) {
+    dbus::listen("Tray", tx, run);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::dbus::peer_to_peer;
+    use image::GenericImageView;
+
+    const MENU_PATH: &str = "/MenuBar";
+
+    struct FakeItem;
+
+    #[interface(name = "org.kde.StatusNotifierItem")]
+    impl FakeItem {
+        #[zbus(property)]
+        fn status(&self) -> String {
+            "Active".to_string()
+        }
+
+        // Empty names are the same as none, so the pixmap is used
+        #[zbus(property)]
+        fn icon_name(&self) -> String {
+            String::new()
+        }
+
+        #[zbus(property)]
+        fn icon_pixmap(&self) -> Vec<(i32, i32, Vec<u8>)> {
+            vec![
+                (1, 1, vec![255, 0, 0, 0]),
+                (
+                    2,
+                    2,
+                    [[255, 10, 20, 30], [255, 40, 50, 60]].concat().repeat(2),
+                ),
+            ]
+        }
+
+        #[zbus(property)]
+        fn menu(&self) -> OwnedObjectPath {
+            OwnedObjectPath::try_from(MENU_PATH).unwrap()
+        }
+    }
+
+    struct FakeMenu;
+
+    #[interface(name = "com.canonical.dbusmenu")]
+    impl FakeMenu {
+        fn about_to_show(&self, _id: i32) -> bool {
+            false
+        }
+
+        fn get_layout(
+            &self,
+            parent: i32,
+            _depth: i32,
+            _properties: Vec<String>,
+        ) -> (u32, (i32, HashMap<String, OwnedValue>, Vec<OwnedValue>)) {
+            let child = |id: i32, properties: Vec<(&str, Value)>| {
+                let properties: HashMap<_, _> = properties.into_iter().collect();
+                OwnedValue::try_from(Value::from((id, properties, Vec::<Value>::new()))).unwrap()
+            };
+            let children = vec![
+                child(1, vec![("label", Value::from("_Open"))]),
+                child(2, vec![("type", Value::from("separator"))]),
+                child(
+                    3,
+                    vec![("label", Value::from("Hidden")), ("visible", false.into())],
+                ),
+                child(
+                    4,
+                    vec![
+                        ("label", Value::from("Recent")),
+                        ("children-display", Value::from("submenu")),
+                    ],
+                ),
+                child(
+                    5,
+                    vec![("label", Value::from("Quit")), ("enabled", false.into())],
+                ),
+            ];
+
+            (1, (parent, HashMap::new(), children))
+        }
+    }
+
+    #[test]
+    fn fake_item() {
+        let (client, _server) = peer_to_peer(ITEM_PATH, FakeItem);
+
+        let item = Item::with_owner(&client, "org.fake.Item", ":1.7").unwrap();
+        assert!(!item.passive);
+        assert_eq!(item.icon_name, None);
+        assert_eq!(
+            item.menu(),
+            Some(("org.fake.Item".into(), MENU_PATH.into()))
+        );
+
+        // Largest pixmap is converted from ARGB to RGBA
+        let icon = item.icon(2).unwrap();
+        assert_eq!(icon.get_pixel(0, 0).0, [10, 20, 30, 255]);
+        assert_eq!(icon.get_pixel(1, 1).0, [40, 50, 60, 255]);
+    }
+
+    #[test]
+    fn fake_menu() {
+        let (client, _server) = peer_to_peer(MENU_PATH, FakeMenu);
+
+        let items = get_layout(&client, "org.fake.Item", MENU_PATH, 0).unwrap();
+        let summary: Vec<_> = items
+            .iter()
+            .map(|item| (item.id, &*item.label, item.enabled, item.submenu))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                (1, "Open", true, false),
+                (4, "Recent", true, true),
+                (5, "Quit", false, false),
+            ]
+        );
+    }
+}